@@ -1,32 +1,54 @@
 use cheers_lib::{
-    board::{tb_adapter::MovegenAdapter, Board},
-    hash_tables::TranspositionTable,
+    board::{
+        eval_params::{set_eval_params, EvalParams, EVAL_PARAMS},
+        evaluate::SCALE_NORMAL,
+        tb_adapter::MovegenAdapter,
+        Board,
+    },
+    hash_tables::{PawnHashTable, TranspositionTable},
     options::SearchOptions,
     pyrrhic_rs::TableBases,
-    search::{Search, ABORT_SEARCH, NODE_COUNT},
+    search::{Search, ABORT_SEARCH, NODE_COUNT, TB_HITS},
     types::Color,
 };
 
 use std::{
     error::Error,
     io::{prelude::*, stdin},
-    sync::{atomic::Ordering, Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     thread::{self, JoinHandle},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 mod uci;
 
+// the search thread goes idle -> active on `go` and back to idle only after printing its
+// `bestmove`, so every `go` gets exactly one. `stop` while idle does nothing. while active,
+// `stop` and a new `go` wait for the thread to finish before reading the next command, so no
+// `bestmove` is ever printed out of order. `quit` waits too, up to `QUIT_TIMEOUT`
+static SEARCH_ACTIVE: AtomicBool = AtomicBool::new(false);
+
 fn main() -> Result<(), Box<dyn Error>> {
     let mut position = Board::new();
     let mut options = SearchOptions::default();
     let mut chess_960 = false;
+    let mut clear_hash_on_jump = false;
+    let mut analyse_mode = false;
+    let mut move_overhead = 50;
 
     let mut tbs = None;
-    let mut running_thread: Option<JoinHandle<_>> = None;
+    let mut running_thread: Option<SearchThread> = None;
 
     let mut tt = Arc::new(RwLock::new(TranspositionTable::new(options.tt_size_mb)));
+    // the search is kept between moves so its history tables stay warm. it's only here while
+    // idle, a running search has it on its thread
+    let mut idle_search = Some(Search::new_with_tt(position, tt.clone()));
     let mut pre_history = Vec::new();
+    // hash of the last position set, to detect GUIs skipping 'ucinewgame' between games
+    let mut last_position_hash = None;
 
     if std::env::args().nth(1) == Some(String::from("bench")) {
         let depth = std::env::args()
@@ -34,18 +56,46 @@ fn main() -> Result<(), Box<dyn Error>> {
             .and_then(|n| n.parse::<usize>().ok())
             .unwrap_or(15);
         let bench_game = position;
-        let search = Search::new(bench_game)
+        let mut search = Search::new(bench_game)
             .max_depth(Some(depth))
             .tt_size_mb(options.tt_size_mb)
             .output(false);
         let start = Instant::now();
-        search.smp_search();
+        let (score, pv, _) = search.smp_search();
         let end = Instant::now();
         let time = end - start;
 
         let nodes = NODE_COUNT.load(Ordering::Relaxed);
-        let nps = (nodes as f64 / time.as_secs_f64()) as usize;
-        println!("{nodes} nodes {nps} nps");
+        let tbhits = TB_HITS.load(Ordering::Relaxed);
+        // the last completed iteration, which can stop short of the requested depth
+        let depth_reached = search.current_best().map_or(0, |(_, _, depth)| depth);
+        let best_move = pv
+            .iter()
+            .next()
+            .map_or(String::from("0000"), |mv| mv.coords());
+        println!(
+            "{}",
+            bench_report(nodes, time, depth_reached, &best_move, score, tbhits)
+        );
+        return Ok(());
+    }
+
+    // OpenBench runs `cheers "genfens N seed S book None" quit` to get its openings
+    if let Some(arg) = std::env::args().nth(1).filter(|a| a.starts_with("genfens")) {
+        match uci::parse_uci_command(arg) {
+            Ok(uci::UciCommand::GenFens { count, seed, plies }) => {
+                match genfens(count, seed, plies, false) {
+                    Ok(fens) => {
+                        for fen in fens {
+                            println!("info string genfens {fen}");
+                        }
+                    }
+                    Err(e) => println!("info string {e}"),
+                }
+            }
+            Err(uci::UciParseError::Other(e)) => eprintln!("{e}"),
+            _ => {}
+        }
         return Ok(());
     }
 
@@ -56,7 +106,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                 continue;
             }
             Err(uci::UciParseError::Other(e)) => {
-                eprintln!("{e}");
+                // report over UCI so GUIs can show it, nothing from the command is applied
+                for line in e.lines() {
+                    println!("info string {}", line.trim());
+                }
                 continue;
             }
         };
@@ -75,7 +128,39 @@ fn main() -> Result<(), Box<dyn Error>> {
                     tt.write().unwrap().set_size(mb);
                 }
                 uci::UciOption::Threads(n) => options.threads = n,
+                uci::UciOption::AllowOversubscription(x) => options.allow_oversubscription = x,
+                uci::UciOption::ThreadAffinity(x) => options.thread_affinity = x,
+                uci::UciOption::EvalTrace(x) => options.eval_trace = x,
+                uci::UciOption::UseAspiration(x) => options.use_aspiration = x,
+                uci::UciOption::AspirationDelta(n) => options.aspiration_delta = n,
+                uci::UciOption::AspirationMinDepth(n) => options.aspiration_min_depth = n,
                 uci::UciOption::UCI_Chess960(x) => chess_960 = x,
+                uci::UciOption::UCI_ShowWDL(x) => options.show_wdl = x,
+                uci::UciOption::UCI_SanPv(x) => options.san_pv = x,
+                uci::UciOption::UCI_AnalyseMode(x) => analyse_mode = x,
+                uci::UciOption::EvalNoise(n) => options.eval_noise = n,
+                uci::UciOption::LimitCheckExtensions(x) => options.limit_check_extensions = x,
+                uci::UciOption::EvalFile(p) => {
+                    let params = if &p == "<empty>" {
+                        Ok(EVAL_PARAMS)
+                    } else {
+                        EvalParams::from_file(&p)
+                    };
+                    match params {
+                        Ok(params) => {
+                            stop_search(&mut running_thread, &mut idle_search, &mut tbs);
+                            set_eval_params(params);
+                            // the pawn hash table has scores from the old parameters
+                            idle_search = None;
+                            if &p != "<empty>" {
+                                println!("info string loaded eval parameters from {p}");
+                            }
+                        }
+                        Err(e) => println!("info string {e}"),
+                    }
+                }
+                uci::UciOption::ClearHashOnJump(x) => clear_hash_on_jump = x,
+                uci::UciOption::MoveOverhead(n) => move_overhead = n,
                 uci::UciOption::SyzygyPath(p) => {
                     // drop old TBs
                     tbs = None;
@@ -92,11 +177,15 @@ fn main() -> Result<(), Box<dyn Error>> {
                 uci::UciOption::NmpDepth(n) => options.nmp_depth = n,
                 uci::UciOption::NmpConstReduction(n) => options.nmp_const_reduction = n,
                 uci::UciOption::NmpLinearDivisor(n) => options.nmp_linear_divisor = n,
+                uci::UciOption::NmpVerificationDepth(n) => options.nmp_verification_depth = n,
                 uci::UciOption::SeePruningDepth(n) => options.see_pruning_depth = n,
                 uci::UciOption::SeeCaptureMargin(n) => options.see_capture_margin = n,
                 uci::UciOption::SeeQuietMargin(n) => options.see_quiet_margin = n,
                 uci::UciOption::PvsFulldepth(n) => options.pvs_fulldepth = n,
                 uci::UciOption::DeltaPruningMargin(n) => options.delta_pruning_margin = n,
+                uci::UciOption::DeltaPruningKeepsChecks(x) => {
+                    options.delta_pruning_keeps_checks = x
+                }
                 uci::UciOption::FpMargin1(n) => options.fp_margin_1 = n,
                 uci::UciOption::FpMargin2(n) => options.fp_margin_2 = n,
                 uci::UciOption::FpMargin3(n) => options.fp_margin_3 = n,
@@ -104,12 +193,23 @@ fn main() -> Result<(), Box<dyn Error>> {
                 uci::UciOption::RfpMargin(n) => options.rfp_margin = n,
                 uci::UciOption::RfpImprovingMargin(n) => options.rfp_improving_margin = n,
                 uci::UciOption::LmpDepth(n) => options.lmp_depth = n,
+                uci::UciOption::LmrBase(n) => options.lmr_base = n,
+                uci::UciOption::LmrDivisor(n) => options.lmr_divisor = n,
                 uci::UciOption::HistoryLmrDivisor(n) => options.history_lmr_divisor = n,
+                uci::UciOption::CaptureHistoryLmrDivisor(n) => {
+                    options.capture_history_lmr_divisor = n
+                }
                 uci::UciOption::IirDepth(n) => options.iir_depth = n,
+                uci::UciOption::TtMoveCutoffDepth(n) => options.tt_move_cutoff_depth = n,
             },
             uci::UciCommand::UciNewGame => {
+                stop_search(&mut running_thread, &mut idle_search, &mut tbs);
+                if let Some(search) = &mut idle_search {
+                    search.new_game();
+                }
                 position = Board::new();
                 pre_history.clear();
+                last_position_hash = None;
                 tt = Arc::new(RwLock::new(TranspositionTable::new(options.tt_size_mb)));
             }
             uci::UciCommand::Position { fen, moves } => {
@@ -122,6 +222,15 @@ fn main() -> Result<(), Box<dyn Error>> {
                     pre_history.push(position.hash());
                     position.make_move(m);
                 }
+
+                if let Some(previous) = last_position_hash {
+                    if clear_hash_on_jump
+                        && is_unrelated_position(previous, &pre_history, position.hash())
+                    {
+                        tt = Arc::new(RwLock::new(TranspositionTable::new(options.tt_size_mb)));
+                    }
+                }
+                last_position_hash = Some(position.hash());
             }
             uci::UciCommand::Go {
                 wtime,
@@ -131,47 +240,40 @@ fn main() -> Result<(), Box<dyn Error>> {
                 movestogo,
                 depth,
                 nodes,
+                softnodes,
                 movetime,
                 infinite,
                 perft,
+                verify,
             } => {
-                // make sure the previous search terminates before starting a new one
-                if let Some(handle) = running_thread.take() {
-                    // retrieve the TB handle to re-use
-                    if let Some(tb) = handle.join().expect("Search thread crashed") {
-                        tbs = Some(tb);
-                    }
-                }
+                // a GUI should stop the previous search first, but make sure it's done either way
+                stop_search(&mut running_thread, &mut idle_search, &mut tbs);
                 if let Some(depth) = perft {
-                    position.perft(depth);
+                    if verify {
+                        position.perft_verify(depth);
+                    } else {
+                        position.perft(depth);
+                    }
                     continue;
                 }
                 let movetime = if infinite {
                     None
                 } else {
                     match movetime {
-                        Some(time) => Some((time, time)),
+                        Some(time) => Some(fixed_move_time(time, move_overhead)),
                         None => match movestogo {
                             Some(n) => {
                                 if position.current_player() == Color::White {
-                                    // add a 50ms margin to avoid timeouts
-                                    Some((
-                                        (wtime.unwrap().max(0) as usize - 50) / n,
-                                        (wtime.unwrap().max(0) as usize - 50) / n,
-                                    ))
+                                    moves_to_go_time(wtime, n, move_overhead)
                                 } else {
-                                    // add a 50ms margin to avoid timeouts
-                                    Some((
-                                        (btime.unwrap().max(0) as usize - 50) / n,
-                                        (btime.unwrap().max(0) as usize - 50) / n,
-                                    ))
+                                    moves_to_go_time(btime, n, move_overhead)
                                 }
                             }
                             None => {
                                 if position.current_player() == Color::White {
-                                    move_time(wtime, winc)
+                                    move_time(wtime, winc, move_overhead)
                                 } else {
-                                    move_time(btime, binc)
+                                    move_time(btime, binc, move_overhead)
                                 }
                             }
                         },
@@ -181,42 +283,346 @@ fn main() -> Result<(), Box<dyn Error>> {
                 // extract the TBs so the search thread can be the sole owner
                 let tb = tbs.take();
 
-                let mut search = Search::new_with_tt(position, tt.clone())
+                let mut search = idle_search
+                    .take()
+                    .unwrap_or_else(|| Search::new_with_tt(position, tt.clone()))
+                    .transposition_table(tt.clone())
                     .tt_size_mb(options.tt_size_mb)
                     .tablebases(tb)
-                    .pre_history(pre_history.clone())
                     .max_nodes(nodes)
+                    .soft_max_nodes(softnodes)
                     .max_depth(depth)
-                    .options(options)
+                    .options(if analyse_mode {
+                        options.analyse_mode()
+                    } else {
+                        options
+                    })
                     .output(true)
                     .chess_960(chess_960);
+                search.set_position(position, pre_history.clone());
                 search.max_time_ms = movetime;
 
+                SEARCH_ACTIVE.store(true, Ordering::SeqCst);
                 running_thread = Some(thread::spawn(move || engine_thread(search).unwrap()));
             }
             uci::UciCommand::Fen => println!("{}", position.fen()),
-            uci::UciCommand::Stop => ABORT_SEARCH.store(true, Ordering::Relaxed),
+            uci::UciCommand::PrintBoard => {
+                println!("{}", board_diagram(&position));
+                println!("Fen: {}", position.fen());
+                println!("Hash: {:016X}", position.hash());
+                println!("In check: {}", position.in_check());
+            }
+            uci::UciCommand::Eval => {
+                // scratch table so the live search state is left untouched
+                let mut pawn_hash_table = PawnHashTable::new();
+                let (score, phase, scale) = position.evaluate_breakdown(&mut pawn_hash_table);
+                let eval = position.evaluate(&mut pawn_hash_table);
+                let white_eval = if position.current_player() == Color::White {
+                    eval
+                } else {
+                    -eval
+                };
+
+                println!("Phase: {phase}/256 (0 = midgame, 256 = endgame)");
+                println!("Midgame: {} cp (white)", score.mg());
+                println!("Endgame: {} cp (white)", score.eg());
+                println!("Endgame scale: {scale}/{SCALE_NORMAL}");
+                println!("Final: {white_eval} cp (white), {eval} cp (side to move)");
+            }
+            uci::UciCommand::CheckHash => println!("{}", check_hash(&position)),
+            uci::UciCommand::GenFens { count, seed, plies } => {
+                match genfens(count, seed, plies, chess_960) {
+                    Ok(fens) => {
+                        for fen in fens {
+                            println!("info string genfens {fen}");
+                        }
+                    }
+                    Err(e) => println!("info string {e}"),
+                }
+            }
+            uci::UciCommand::BestMoveTest { path, movetime } => {
+                stop_search(&mut running_thread, &mut idle_search, &mut tbs);
+                let epd = match std::fs::read_to_string(&path) {
+                    Ok(epd) => epd,
+                    Err(e) => {
+                        println!("info string Couldn't read {path}: {e}");
+                        continue;
+                    }
+                };
+                let results = bestmove_test(&epd, movetime, options);
+                for result in &results {
+                    let status = if result.solved { "ok" } else { "fail" };
+                    println!(
+                        "info string bestmove-test {} {} {status} ({})",
+                        result.id, result.played, result.expected
+                    );
+                }
+                let solved = results.iter().filter(|r| r.solved).count();
+                println!(
+                    "info string bestmove-test solved {solved}/{}",
+                    results.len()
+                );
+            }
+            uci::UciCommand::Stop => stop_search(&mut running_thread, &mut idle_search, &mut tbs),
             uci::UciCommand::Quit => {
-                ABORT_SEARCH.store(true, Ordering::Relaxed);
+                quit_search(&mut running_thread);
                 break;
             }
         }
     }
+    // stdin closing mid-search still gets the bestmove out
+    stop_search(&mut running_thread, &mut idle_search, &mut tbs);
     Ok(())
 }
 
-fn engine_thread(search: Search) -> Result<Option<TableBases<MovegenAdapter>>, Box<dyn Error>> {
+// the search handed back by a finished search thread, with the TBs it borrowed
+type FinishedSearch = (Search, Option<TableBases<MovegenAdapter>>);
+type SearchThread = JoinHandle<FinishedSearch>;
+
+// abort the running search and wait for its `bestmove`, taking back the search and the TBs for
+// re-use. the search clears the abort flag when it starts, so keep raising it until the thread
+// is done in case the stop arrived before the search did
+fn stop_search(
+    running_thread: &mut Option<SearchThread>,
+    idle_search: &mut Option<Search>,
+    tbs: &mut Option<TableBases<MovegenAdapter>>,
+) {
+    let Some(handle) = running_thread.take() else {
+        return;
+    };
+    while SEARCH_ACTIVE.load(Ordering::SeqCst) && !handle.is_finished() {
+        ABORT_SEARCH.store(true, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(1));
+    }
+    let (search, tb) = handle.join().expect("Search thread crashed");
+    *idle_search = Some(search);
+    if tb.is_some() {
+        *tbs = tb;
+    }
+}
+
+// how long `quit` waits for a running search to print its `bestmove` and hand back the TBs
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// abort the running search for `quit`. joining lets the `bestmove` out and drops the TBs with
+// the search, but a search that doesn't stop in time shouldn't keep the engine from exiting
+fn quit_search(running_thread: &mut Option<SearchThread>) {
+    let Some(handle) = running_thread.take() else {
+        return;
+    };
+    let deadline = Instant::now() + QUIT_TIMEOUT;
+    while !handle.is_finished() && Instant::now() < deadline {
+        ABORT_SEARCH.store(true, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(1));
+    }
+    if handle.is_finished() {
+        handle.join().expect("Search thread crashed");
+    }
+}
+
+fn engine_thread(mut search: Search) -> Result<FinishedSearch, Box<dyn Error>> {
     ABORT_SEARCH.store(false, Ordering::Relaxed);
     NODE_COUNT.store(0, Ordering::Relaxed);
 
     let (_, pv, tbs) = search.smp_search();
 
-    println!("bestmove {}", pv[0].coords());
+    // the PV only comes back empty with no legal moves at the root
+    match pv.iter().next() {
+        Some(mv) => println!("bestmove {}", mv.coords()),
+        None => println!("bestmove 0000"),
+    }
+    SEARCH_ACTIVE.store(false, Ordering::SeqCst);
+
+    Ok((search, tbs))
+}
+
+// ascii board with white at the bottom, built from the fen piece placement
+fn board_diagram(board: &Board) -> String {
+    let separator = " +---+---+---+---+---+---+---+---+\n";
+    let mut diagram = String::new();
+    let fen = board.fen();
+    let placement = fen.split(' ').next().unwrap_or_default();
+    for (rank, row) in placement.split('/').enumerate() {
+        diagram += separator;
+        for c in row.chars() {
+            match c.to_digit(10) {
+                Some(empty) => diagram += &" |  ".repeat(empty as usize),
+                None => diagram += &format!(" | {c}"),
+            }
+        }
+        diagram += &format!(" | {}\n", 8 - rank);
+    }
+    diagram += separator;
+    diagram += "   a   b   c   d   e   f   g   h";
+    diagram
+}
+
+// compare the incrementally updated hashes against ones computed from scratch
+fn check_hash(board: &Board) -> String {
+    let mut mismatches = Vec::new();
+    for (name, incremental, calculated) in [
+        ("hash", board.hash(), board.calculate_hash()),
+        ("pawn hash", board.pawn_hash(), board.calculate_pawn_hash()),
+    ] {
+        if incremental != calculated {
+            mismatches.push(format!(
+                "{name} mismatch: incremental {incremental:016X}, calculated {calculated:016X}"
+            ));
+        }
+    }
+
+    if mismatches.is_empty() {
+        String::from("ok")
+    } else {
+        mismatches.join("\n")
+    }
+}
+
+const GENFENS_MAX_ATTEMPTS: usize = 1000;
 
-    Ok(tbs)
+// `count` openings of `plies` random legal moves from the start position, or a random Chess960
+// one. the same seed always gives the same list
+fn genfens(count: usize, seed: u64, plies: usize, chess_960: bool) -> Result<Vec<String>, String> {
+    // splitmix64
+    let mut state = seed;
+    let mut random = move || {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        (z ^ (z >> 31)) as usize
+    };
+
+    let mut fens = Vec::with_capacity(count);
+    let mut failed_attempts = 0;
+    'playout: while fens.len() < count {
+        // long playouts nearly always end in a finished game, so give up rather than spin
+        if failed_attempts == GENFENS_MAX_ATTEMPTS {
+            return Err(format!(
+                "genfens gave up after {GENFENS_MAX_ATTEMPTS} finished games in a row, try fewer plies"
+            ));
+        }
+        let mut board = if chess_960 {
+            Board::frc(random() % 960)
+        } else {
+            Board::new()
+        };
+        for _ in 0..plies {
+            // a game that's already over is no use as an opening, roll another
+            if board.game_result().is_some() {
+                failed_attempts += 1;
+                continue 'playout;
+            }
+            let moves = board.legal_moves();
+            board.make_move(moves[random() % moves.len()]);
+        }
+        if board.game_result().is_some() {
+            failed_attempts += 1;
+            continue;
+        }
+        failed_attempts = 0;
+        fens.push(board.fen());
+    }
+    Ok(fens)
+}
+
+// testing frameworks read the node count from the final `Bench: <nodes>` line to check a
+// commit's signature, and `<nodes> nodes <nps> nps` from the line before it. The rest of that
+// line is for comparing the search results between commits
+fn bench_report(
+    nodes: usize,
+    time: Duration,
+    depth: usize,
+    best_move: &str,
+    score: i16,
+    tbhits: usize,
+) -> String {
+    let nps = (nodes as f64 / time.as_secs_f64()) as usize;
+    format!(
+        "{nodes} nodes {nps} nps depth {depth} bestmove {best_move} score cp {score} tbhits {tbhits}\nBench: {nodes}"
+    )
+}
+
+struct BestMoveTestResult {
+    id: String,
+    // the engine's move in SAN, to compare with the suite
+    played: String,
+    // the `bm` and `am` operations
+    expected: String,
+    solved: bool,
+}
+
+// search each EPD position for `movetime` ms. A position is solved if the move played is one
+// of its `bm` moves and none of its `am` moves, compared as SAN without check marks
+fn bestmove_test(epd: &str, movetime: usize, options: SearchOptions) -> Vec<BestMoveTestResult> {
+    let strip = |san: &str| san.trim_end_matches(['+', '#', '!', '?']).to_string();
+    let mut results = Vec::new();
+    for (i, line) in epd.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((board, ops)) = Board::from_epd(line) else {
+            results.push(BestMoveTestResult {
+                id: format!("line{}", i + 1),
+                played: String::from("-"),
+                expected: String::from("invalid EPD"),
+                solved: false,
+            });
+            continue;
+        };
+
+        let mut search = Search::new(board)
+            .tt_size_mb(options.tt_size_mb)
+            .options(options)
+            .output(false);
+        search.max_time_ms = Some((movetime, movetime));
+        let (_, pv, _) = search.smp_search();
+        let played = pv
+            .iter()
+            .next()
+            .map_or(String::from("-"), |&mv| strip(&board.move_to_san(mv)));
+
+        let moves = |opcode| -> Vec<String> {
+            ops.get(opcode)
+                .map_or(Vec::new(), |m| m.split_whitespace().map(strip).collect())
+        };
+        let (best, avoid) = (moves("bm"), moves("am"));
+        let solved = (best.is_empty() || best.contains(&played)) && !avoid.contains(&played);
+        let expected = [("bm", &best), ("am", &avoid)]
+            .iter()
+            .filter(|(_, moves)| !moves.is_empty())
+            .map(|(opcode, moves)| format!("{opcode} {}", moves.join(" ")))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        results.push(BestMoveTestResult {
+            id: ops
+                .get("id")
+                .cloned()
+                .unwrap_or_else(|| format!("line{}", i + 1)),
+            played,
+            expected,
+            solved,
+        });
+    }
+    results
+}
+
+// the previous position not appearing anywhere in the new game's history means the GUI has
+// moved on to an unrelated game or analysis position
+fn is_unrelated_position(previous_hash: u64, history: &[u64], current_hash: u64) -> bool {
+    current_hash != previous_hash && !history.contains(&previous_hash)
 }
 
-fn move_time(time_millis: Option<isize>, inc_millis: Option<isize>) -> Option<(usize, usize)> {
+// (soft, hard) limits: the search stops starting new iterations after the soft limit (scaled
+// by nodetm in the search) and aborts outright at the hard limit. Both are computed from the
+// clock left after the move overhead so the hard limit can never overrun the clock
+fn move_time(
+    time_millis: Option<isize>,
+    inc_millis: Option<isize>,
+    overhead: usize,
+) -> Option<(usize, usize)> {
     let (time, inc) = match (time_millis, inc_millis) {
         (None, None) => return None,
         (t, i) => (
@@ -224,9 +630,166 @@ fn move_time(time_millis: Option<isize>, inc_millis: Option<isize>) -> Option<(u
             i.unwrap_or(0).max(0) as usize,
         ),
     };
-    if time < inc {
-        Some((time / 20, time / 2))
+    let usable = time.saturating_sub(overhead);
+    let hard = usable / 2;
+    let soft = if time < inc {
+        usable / 20
     } else {
-        Some((time / 20 + inc / 2, time / 2))
+        usable / 20 + inc / 2
+    };
+    Some((soft.min(hard), hard))
+}
+
+// never let the overhead eat more than half of a small fixed move time
+fn fixed_move_time(movetime: usize, overhead: usize) -> (usize, usize) {
+    let time = movetime - overhead.min(movetime / 2);
+    (time, time)
+}
+
+// share the clock left after the move overhead evenly between the moves to the time control.
+// with the clock nearly gone this gives a zero limit, which the search treats as a depth 1
+// emergency move
+fn moves_to_go_time(
+    time_millis: Option<isize>,
+    movestogo: usize,
+    overhead: usize,
+) -> Option<(usize, usize)> {
+    let usable = (time_millis?.max(0) as usize).saturating_sub(overhead);
+    let time = usable / movestogo.max(1);
+    Some((time, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cheers_lib::{board::Board, moves::Move, types::Color};
+
+    use super::{
+        bench_report, board_diagram, check_hash, fixed_move_time, genfens, is_unrelated_position,
+        move_time, moves_to_go_time,
+    };
+
+    #[test]
+    fn test_unrelated_position_detection() {
+        let startpos = Board::new();
+
+        // continuing the same game from the previous position
+        let mut history = Vec::new();
+        let mut board = startpos;
+        for mv in ["e2e4", "e7e5"] {
+            history.push(board.hash());
+            board.make_move(Move::from_pair(&board, mv));
+        }
+        assert!(!is_unrelated_position(
+            startpos.hash(),
+            &history,
+            board.hash()
+        ));
+        assert!(!is_unrelated_position(board.hash(), &history, board.hash()));
+
+        // a fresh position with no shared history
+        let unrelated =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        assert!(is_unrelated_position(board.hash(), &[], unrelated.hash()));
+    }
+
+    #[test]
+    fn test_move_time_limits() {
+        assert_eq!(move_time(None, None, 50), None);
+        assert_eq!(move_time(Some(60_000), Some(1000), 50), Some((3497, 29975)));
+
+        for time in [0, 1, 10, 49, 50, 51, 100, 1000] {
+            for inc in [0, 10, 1000] {
+                for overhead in [0, 50, 100] {
+                    let (soft, hard) = move_time(Some(time), Some(inc), overhead).unwrap();
+                    let remaining = (time as usize).saturating_sub(overhead);
+                    assert!(hard <= remaining, "{time}ms +{inc}ms: hard limit {hard}ms");
+                    assert!(soft <= hard, "{time}ms +{inc}ms: soft limit {soft}ms");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_moves_to_go_time() {
+        assert_eq!(moves_to_go_time(None, 10, 50), None);
+        assert_eq!(moves_to_go_time(Some(60_050), 40, 50), Some((1500, 1500)));
+
+        // nearly flagging, or a GUI sending movestogo 0
+        assert_eq!(moves_to_go_time(Some(10), 1, 50), Some((0, 0)));
+        assert_eq!(moves_to_go_time(Some(-100), 1, 50), Some((0, 0)));
+        assert_eq!(moves_to_go_time(Some(1050), 0, 50), Some((1000, 1000)));
+    }
+
+    #[test]
+    fn test_fixed_move_time() {
+        assert_eq!(fixed_move_time(1000, 50), (950, 950));
+        assert_eq!(fixed_move_time(60, 50), (30, 30));
+        assert_eq!(fixed_move_time(1, 50), (1, 1));
+        assert_eq!(fixed_move_time(0, 50), (0, 0));
+    }
+
+    #[test]
+    fn test_board_diagram() {
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let diagram = board_diagram(&board);
+        let lines: Vec<&str> = diagram.lines().collect();
+
+        assert_eq!(lines.len(), 18);
+        assert_eq!(lines[1], " |   |   |   |   | k |   |   |   | 8");
+        assert_eq!(lines[7], " |   |   |   | p | P |   |   |   | 5");
+        assert_eq!(lines[17], "   a   b   c   d   e   f   g   h");
+    }
+
+    #[test]
+    fn test_check_hash() {
+        let mut board = Board::new();
+        for mv in [
+            "e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "d5c6", "d8d2", "b1d2",
+        ] {
+            board.make_move(Move::from_pair(&board, mv));
+        }
+        assert_eq!(check_hash(&board), "ok");
+    }
+
+    #[test]
+    fn test_bench_report() {
+        let report = bench_report(910954, Duration::from_millis(500), 15, "e2e4", 31, 0);
+        assert_eq!(
+            report,
+            "910954 nodes 1821908 nps depth 15 bestmove e2e4 score cp 31 tbhits 0\nBench: 910954"
+        );
+
+        // after the node count and nps each value follows its name, like in `info` lines
+        let (summary, signature) = report.split_once('\n').unwrap();
+        let fields: Vec<_> = summary.split(' ').collect();
+        let value = |name| fields[fields.iter().position(|&f| f == name).unwrap() + 1];
+        assert_eq!(fields[..4], ["910954", "nodes", "1821908", "nps"]);
+        assert_eq!(value("depth").parse(), Ok(15));
+        assert_eq!(value("bestmove"), "e2e4");
+        assert_eq!(value("cp").parse(), Ok(31));
+        assert_eq!(value("tbhits").parse(), Ok(0));
+        assert_eq!(signature.strip_prefix("Bench: "), Some("910954"));
+    }
+
+    #[test]
+    fn test_genfens() {
+        let fens = genfens(20, 1234, 8, false).unwrap();
+        assert_eq!(fens, genfens(20, 1234, 8, false).unwrap());
+        assert_ne!(fens, genfens(20, 1235, 8, false).unwrap());
+        for fen in &fens {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(!board.legal_moves().is_empty(), "{fen}");
+            assert_eq!(board.current_player(), Color::White, "{fen}");
+        }
+
+        let frc = genfens(20, 1234, 0, true).unwrap();
+        assert_eq!(frc, genfens(20, 1234, 0, true).unwrap());
+        assert!(frc.iter().any(|fen| fen != &Board::frc(518).fen()));
+
+        // random games this long are all over before the last ply, so this has to give up
+        assert!(genfens(1, 1, 3000, false).is_err());
     }
 }
@@ -14,16 +14,32 @@ mod macros;
 uci_options![
     Hash(Spin<usize> { default: 32, min: 1, max: 32768 }),
     Threads(Spin<usize> { default: 1, min: 1, max: 256 }),
+    AllowOversubscription(Check { default: false }),
+    ThreadAffinity(Check { default: false }),
+    EvalTrace(Check { default: false }),
+    UseAspiration(Check { default: true }),
+    AspirationDelta(Spin<i16> { default: ASPIRATION_DELTA, min: 1, max: 500 }),
+    AspirationMinDepth(Spin<i8> { default: ASPIRATION_MIN_DEPTH, min: 2, max: 20 }),
     UCI_Chess960(Check { default: false }),
+    UCI_ShowWDL(Check { default: false }),
+    UCI_SanPv(Check { default: false }),
+    UCI_AnalyseMode(Check { default: false }),
+    EvalNoise(Spin<i16> { default: 0, min: 0, max: 200 }),
+    LimitCheckExtensions(Check { default: false }),
     SyzygyPath(OptionString { default: "<empty>" }),
+    EvalFile(OptionString { default: "<empty>" }),
+    ClearHashOnJump(Check { default: false }),
+    MoveOverhead(Spin<usize> { default: 50, min: 0, max: 5000 }),
     NmpDepth(Spin<i8> { default: NMP_DEPTH, min: 1, max: 10 }),
     NmpConstReduction(Spin<i8> { default: NMP_CONST_REDUCTION, min: 1, max: 10 }),
     NmpLinearDivisor(Spin<i8> { default: NMP_LINEAR_DIVISOR, min: 1, max: 10 }),
+    NmpVerificationDepth(Spin<i8> { default: NMP_VERIFICATION_DEPTH, min: 0, max: 64 }),
     SeePruningDepth(Spin<i8> { default: SEE_PRUNING_DEPTH, min: 1, max: 20 }),
     SeeCaptureMargin(Spin<i16> { default: SEE_CAPTURE_MARGIN, min: -200, max: 200 }),
     SeeQuietMargin(Spin<i16> { default: SEE_QUIET_MARGIN, min: -100, max: 100 }),
     PvsFulldepth(Spin<i8> { default: PVS_FULLDEPTH, min: 1, max: 5 }),
     DeltaPruningMargin(Spin<i16> { default: DELTA_PRUNING_MARGIN, min: 0, max: 300 }),
+    DeltaPruningKeepsChecks(Check { default: false }),
     FpMargin1(Spin<i16> { default: FP_MARGIN_1, min: 0, max: 300 }),
     FpMargin2(Spin<i16> { default: FP_MARGIN_2, min: 0, max: 700 }),
     FpMargin3(Spin<i16> { default: FP_MARGIN_3, min: 500, max: 1000 }),
@@ -31,8 +47,12 @@ uci_options![
     RfpMargin(Spin<i16> { default: RFP_MARGIN, min: 0, max: 300 }),
     RfpImprovingMargin(Spin<i16> { default: RFP_IMPROVING_MARGIN, min: -100, max: 100 }),
     LmpDepth(Spin<i8> { default: LMP_DEPTH, min: 0, max: 20 }),
+    LmrBase(Spin<i16> { default: LMR_BASE, min: 0, max: 500 }),
+    LmrDivisor(Spin<i16> { default: LMR_DIVISOR, min: 1, max: 10000 }),
     HistoryLmrDivisor(Spin<i16> { default: HISTORY_LMR_DIVISOR, min: 0, max: 8192 }),
+    CaptureHistoryLmrDivisor(Spin<i16> { default: CAPTURE_HISTORY_LMR_DIVISOR, min: 0, max: 8192 }),
     IirDepth(Spin<i8> { default: IIR_DEPTH, min: 2, max: 20 }),
+    TtMoveCutoffDepth(Spin<i8> { default: TT_MOVE_CUTOFF_DEPTH, min: 0, max: 6 }),
 ];
 
 pub enum UciCommand {
@@ -52,11 +72,25 @@ pub enum UciCommand {
         movestogo: Option<usize>,
         depth: Option<usize>,
         nodes: Option<usize>,
+        softnodes: Option<usize>,
         movetime: Option<usize>,
         infinite: bool,
         perft: Option<usize>,
+        verify: bool,
     },
     Fen,
+    PrintBoard,
+    Eval,
+    CheckHash,
+    GenFens {
+        count: usize,
+        seed: u64,
+        plies: usize,
+    },
+    BestMoveTest {
+        path: String,
+        movetime: usize,
+    },
     Stop,
     Quit,
 }
@@ -98,7 +132,12 @@ impl<T: FromStr + PartialOrd + Display> ValidateOption for Spin<T> {
     type Output = T;
     fn validate<S: AsRef<str>>(&self, data: S) -> StrValidResult<Self::Output> {
         let value = data.as_ref().parse::<T>().map_err(|_| {
-            UciParseError::Other(format!("Invalid value for spin option: {}", data.as_ref()))
+            UciParseError::Other(format!(
+                "Invalid value for spin option: '{}'. Expected a number in the range [{}, {}]",
+                data.as_ref(),
+                self.min,
+                self.max
+            ))
         })?;
 
         if value >= self.min && value <= self.max {
@@ -326,11 +365,9 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
                                     } else {
                                         move_string.to_string()
                                     };
-                                    if test
-                                        .legal_move_list()
-                                        .iter()
-                                        .map(|m| m.coords_960())
-                                        .any(|m| m == move_string)
+                                    if (&test.legal_moves())
+                                        .into_iter()
+                                        .any(|smv| smv.mv.coords_960() == move_string)
                                     {
                                         let checked_move = Move::from_pair(&test, move_string);
                                         test.make_move(checked_move);
@@ -372,9 +409,11 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
                     parse_uci_go_value!(words, movestogo, usize);
                     parse_uci_go_value!(words, depth, usize);
                     parse_uci_go_value!(words, nodes, usize);
+                    parse_uci_go_value!(words, softnodes, usize);
                     parse_uci_go_value!(words, movetime, usize);
 
                     parse_uci_go_value!(words, perft, usize);
+                    let verify = words.contains(&"verify");
 
                     let infinite = words.iter().find(|&&s| s == "infinite");
                     if infinite.is_some()
@@ -385,6 +424,7 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
                             || movestogo.is_some()
                             || depth.is_some()
                             || nodes.is_some()
+                            || softnodes.is_some()
                             || movetime.is_some())
                     {
                         return Err(UciParseError::Other("Error in UCI go command: 'infinite' specified along with other search directives".to_string()));
@@ -398,6 +438,7 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
                             || movestogo.is_some()
                             || depth.is_some()
                             || nodes.is_some()
+                            || softnodes.is_some()
                             || movetime.is_some()
                             || infinite.is_some())
                     {
@@ -412,12 +453,60 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
                         movestogo,
                         depth,
                         nodes,
+                        softnodes,
                         movetime,
                         infinite: infinite.is_some(),
                         perft,
+                        verify,
                     })
                 }
                 "fen" => Ok(Fen),
+                "d" => Ok(PrintBoard),
+                "eval" => Ok(Eval),
+                "checkhash" => Ok(CheckHash),
+                "genfens" => {
+                    let count = match words.get(1).map(|n| n.parse::<usize>()) {
+                        Some(Ok(count)) => count,
+                        _ => {
+                            return Err(UciParseError::Other(
+                                "Expected a position count in genfens command".to_string(),
+                            ))
+                        }
+                    };
+                    // OpenBench also passes `book <path>`, openings here always come from the
+                    // start position so it's ignored
+                    let mut seed = 0;
+                    let mut plies = 8;
+                    for pair in words[2..].chunks(2) {
+                        let value = pair.get(1).ok_or_else(|| {
+                            UciParseError::Other(format!(
+                                "Missing value for {} in genfens command",
+                                pair[0]
+                            ))
+                        })?;
+                        let invalid = || {
+                            UciParseError::Other(format!(
+                                "Invalid value for {} in genfens command: {value}",
+                                pair[0]
+                            ))
+                        };
+                        match pair[0] {
+                            "seed" => seed = value.parse().map_err(|_| invalid())?,
+                            "plies" => plies = value.parse().map_err(|_| invalid())?,
+                            _ => {}
+                        }
+                    }
+                    Ok(GenFens { count, seed, plies })
+                }
+                "bestmove-test" => match (words.get(1), words.get(2).map(|t| t.parse())) {
+                    (Some(path), Some(Ok(movetime))) => Ok(BestMoveTest {
+                        path: path.to_string(),
+                        movetime,
+                    }),
+                    _ => Err(UciParseError::Other(
+                        "Expected an EPD file and a movetime in bestmove-test command".to_string(),
+                    )),
+                },
                 "stop" => Ok(Stop),
                 "quit" => Ok(Quit),
                 other => Err(UciParseError::Other(format!(
@@ -429,3 +518,24 @@ pub fn parse_uci_command<T: AsRef<str>>(cmd: T) -> Result<UciCommand, UciParseEr
         None => Err(UciParseError::Empty),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_uci_command, UciCommand, UciOption, UciParseError};
+
+    #[test]
+    fn test_invalid_spin_value() {
+        for value in ["abc", "0", "40000"] {
+            let command = format!("setoption name Hash value {value}");
+            match parse_uci_command(command) {
+                Err(UciParseError::Other(e)) => assert!(e.contains("[1, 32768]"), "{e}"),
+                _ => panic!("Invalid Hash value {value} was accepted"),
+            }
+        }
+
+        assert!(matches!(
+            parse_uci_command("setoption name Hash value 64"),
+            Ok(UciCommand::SetOption(UciOption::Hash(64)))
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// run the engine binary over a scripted UCI session and collect its output
+fn run_script(script: &str) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cheers"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+
+    // a lost stop leaves an infinite search running forever, fail instead of hanging
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).unwrap();
+        sender.send(output)
+    });
+    let output = receiver.recv_timeout(Duration::from_secs(60));
+    if output.is_err() {
+        child.kill().unwrap();
+    }
+    child.wait().unwrap();
+
+    output
+        .expect("UCI session hung")
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_one_bestmove_per_go() {
+    let output = run_script(
+        "uci\n\
+         stop\n\
+         position startpos\n\
+         go depth 2\n\
+         go infinite\n\
+         stop\n\
+         go infinite\n\
+         stop\n\
+         stop\n\
+         isready\n\
+         go depth 1\n\
+         quit\n",
+    );
+
+    let bestmoves: Vec<usize> = output
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("bestmove "))
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(bestmoves.len(), 4, "{output:#?}");
+
+    // stop only returns once the bestmove is out
+    let ready = output.iter().position(|line| line == "readyok").unwrap();
+    assert!(bestmoves[2] < ready && ready < bestmoves[3]);
+}
+
+#[test]
+fn test_stop_when_idle() {
+    let output = run_script("stop\nisready\nstop\nquit\n");
+    assert_eq!(output, ["readyok"]);
+}
+
+#[test]
+fn test_quit_during_search() {
+    let start = Instant::now();
+    let output = run_script("position startpos\ngo infinite\nquit\n");
+    let bestmoves = output
+        .iter()
+        .filter(|line| line.starts_with("bestmove "))
+        .count();
+    assert_eq!(bestmoves, 1, "{output:#?}");
+
+    // quit before any search exits straight away, with nothing to print
+    let output = run_script("quit\nisready\n");
+    assert!(output.is_empty(), "{output:#?}");
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_bestmove_test() {
+    let suite = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tactics.epd");
+    let output = run_script(&format!("bestmove-test {suite} 200\nquit\n"));
+    let results: Vec<_> = output
+        .iter()
+        .filter_map(|line| line.strip_prefix("info string bestmove-test "))
+        .collect();
+    assert_eq!(results.len(), 8, "{output:#?}");
+
+    // the mates in one and the poisoned pawn are found straight away
+    for id in ["mate.1", "mate.2", "poisoned.1"] {
+        assert!(
+            results
+                .iter()
+                .any(|r| r.starts_with(id) && r.contains(" ok ")),
+            "{id} not solved: {results:#?}"
+        );
+    }
+    assert!(results[7].starts_with("solved "), "{results:#?}");
+
+    let output = run_script("bestmove-test /no/such/suite.epd 10\nquit\n");
+    assert!(
+        output[0].starts_with("info string Couldn't read"),
+        "{output:#?}"
+    );
+}
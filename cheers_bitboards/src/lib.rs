@@ -39,6 +39,22 @@ impl BitBoard {
     pub fn count_ones(&self) -> u32 {
         self.0.count_ones()
     }
+    // iterates over a copy of the bitboard, so unlike popping squares with
+    // `clear_first_square` this leaves `self` untouched
+    #[inline(always)]
+    pub fn squares(&self) -> BitBoardIter {
+        self.into_iter()
+    }
+    // writes the set squares in ascending order into `buf`, as many as fit, and returns how
+    // many were written
+    pub fn fill_squares(&self, buf: &mut [Square]) -> usize {
+        let mut written = 0;
+        for (slot, square) in buf.iter_mut().zip(self.squares()) {
+            *slot = square;
+            written += 1;
+        }
+        written
+    }
     #[inline(always)]
     pub fn ishift(&self, n: i32) -> Self {
         if n > 0 {
@@ -49,29 +65,55 @@ impl BitBoard {
     }
 }
 
-impl Iterator for BitBoard {
+// iterating a bitboard walks a copy of it, so the original is never emptied
+impl IntoIterator for BitBoard {
+    type Item = Square;
+    type IntoIter = BitBoardIter;
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        BitBoardIter(self.0)
+    }
+}
+
+impl IntoIterator for &BitBoard {
+    type Item = Square;
+    type IntoIter = BitBoardIter;
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        BitBoardIter(self.0)
+    }
+}
+
+/// The set squares of a [`BitBoard`], lowest first.
+#[derive(Clone, Copy, Debug)]
+pub struct BitBoardIter(u64);
+
+impl Iterator for BitBoardIter {
     type Item = Square;
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
         if self.0 == 0 {
             None
         } else {
-            let sq = Some(self.first_square());
-            self.clear_first_square();
-            sq
+            let sq = Square(self.0.trailing_zeros() as u8);
+            self.0 &= self.0 - 1;
+            Some(sq)
         }
     }
     #[inline(always)]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.count_ones() as usize, Some(self.count_ones() as usize))
+        (
+            self.0.count_ones() as usize,
+            Some(self.0.count_ones() as usize),
+        )
     }
 
     #[inline(always)]
     fn count(self) -> usize {
-        self.count_ones() as usize
+        self.0.count_ones() as usize
     }
 }
-impl ExactSizeIterator for BitBoard {}
+impl ExactSizeIterator for BitBoardIter {}
 
 impl Display for BitBoard {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -174,8 +216,8 @@ mod consts {
     pub const SEVENTH_RANK: BitBoard = BitBoard(0x00FF000000000000);
     pub const EIGHTH_RANK: BitBoard = BitBoard(0xFF00000000000000);
 
-    pub const LIGHT_SQUARES: BitBoard = BitBoard(0x5555555555555555);
-    pub const DARK_SQUARES: BitBoard = BitBoard(0xAAAAAAAAAAAAAAAA);
+    pub const LIGHT_SQUARES: BitBoard = BitBoard(0x55AA55AA55AA55AA);
+    pub const DARK_SQUARES: BitBoard = BitBoard(0xAA55AA55AA55AA55);
 
     pub const FULL_BOARD: BitBoard = BitBoard(0xFFFFFFFFFFFFFFFF);
 
@@ -283,9 +325,28 @@ impl Square {
         }
     }
 
+    // unchecked in release builds, a debug build panics if the result is off the board
     #[inline(always)]
     pub fn offset(&self, file: i8, rank: i8) -> Self {
-        Self((self.0 as i8 + rank * 8 + file) as u8)
+        let index = self.0 as i8 + rank * 8 + file;
+        debug_assert!(
+            (0..64).contains(&index),
+            "offset ({file}, {rank}) from square {} is off the board",
+            self.0
+        );
+        Self(index as u8)
+    }
+
+    // none if the file or rank would leave the board, rather than wrapping onto the next rank
+    #[inline(always)]
+    pub fn offset_checked(&self, file: i8, rank: i8) -> Option<Self> {
+        let new_file = self.file() as i8 + file;
+        let new_rank = self.rank() as i8 + rank;
+        if (0..8).contains(&new_file) && (0..8).contains(&new_rank) {
+            Some(Self((new_rank * 8 + new_file) as u8))
+        } else {
+            None
+        }
     }
 
     pub fn coord(&self) -> String {
@@ -337,6 +398,7 @@ impl<T, const N: usize> Index<Square> for [T; N] {
 
     #[inline(always)]
     fn index(&self, index: Square) -> &Self::Output {
+        debug_assert!(index.0 < 64, "indexing with off-board square {}", index.0);
         &self[index.0 as usize]
     }
 }
@@ -344,6 +406,7 @@ impl<T, const N: usize> Index<Square> for [T; N] {
 impl<T, const N: usize> IndexMut<Square> for [T; N] {
     #[inline(always)]
     fn index_mut(&mut self, index: Square) -> &mut Self::Output {
+        debug_assert!(index.0 < 64, "indexing with off-board square {}", index.0);
         &mut self[index.0 as usize]
     }
 }
@@ -369,3 +432,74 @@ square_from_impl!(i16);
 square_from_impl!(i32);
 square_from_impl!(i64);
 square_from_impl!(isize);
+
+#[cfg(test)]
+mod tests {
+    use super::{BitBoard, Square, LONG_DIAGONALS};
+
+    #[test]
+    fn test_iteration() {
+        let bb = LONG_DIAGONALS;
+        assert_eq!(bb.squares().count(), bb.count_ones() as usize);
+        assert_eq!(bb.squares().next(), Some(Square::A1));
+        assert_eq!(bb, LONG_DIAGONALS);
+
+        let mut buf = [Square::NULL; 16];
+        assert_eq!(bb.fill_squares(&mut buf), 16);
+        assert!(buf.windows(2).all(|w| w[0] < w[1]));
+        assert!(buf.iter().all(|sq| (bb & sq.bitboard()).is_not_empty()));
+
+        // by value, by reference and through an explicit iterator all visit every square
+        let mut visited = 0;
+        for _ in bb {
+            visited += 1;
+        }
+        for _ in &bb {
+            visited += 1;
+        }
+        let mut iter = bb.into_iter();
+        assert_eq!(iter.len(), 16);
+        while iter.next().is_some() {
+            visited += 1;
+        }
+        assert_eq!(visited, 3 * 16);
+        assert_eq!(bb, LONG_DIAGONALS);
+
+        let mut short = [Square::NULL; 3];
+        assert_eq!(bb.fill_squares(&mut short), 3);
+        assert_eq!(short, [Square::A1, Square::H1, Square::B2]);
+        assert_eq!(BitBoard::empty().fill_squares(&mut short), 0);
+        assert_eq!(bb, LONG_DIAGONALS);
+    }
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(Square::E2.offset(0, 2), Square::E4);
+        assert_eq!(Square::D5.offset(-1, -1), Square::C4);
+    }
+
+    #[test]
+    fn test_offset_checked() {
+        assert_eq!(Square::H1.offset_checked(1, 0), None);
+        assert_eq!(Square::A2.offset_checked(-1, 0), None);
+        assert_eq!(Square::H8.offset_checked(0, 1), None);
+        assert_eq!(Square::E1.offset_checked(0, -1), None);
+        assert_eq!(Square::G1.offset_checked(1, 1), Some(Square::H2));
+        assert_eq!(Square::B7.offset_checked(-1, 1), Some(Square::A8));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_offset_off_board() {
+        Square::A8.offset(0, 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_index_null_square() {
+        let table = [0u8; 65];
+        let _ = table[Square::NULL];
+    }
+}
@@ -0,0 +1,30 @@
+/// Pins the calling thread to a core. Search threads go through this so tests can stand in
+/// for the OS and check which cores were asked for.
+pub trait ThreadPinner: Sync {
+    // returns whether the thread was actually pinned
+    fn pin_current_thread(&self, core: usize) -> bool;
+}
+
+/// Best-effort pinning through the OS scheduler. Only Linux is supported, on other
+/// platforms threads are left where the OS puts them.
+pub struct OsPinner;
+
+impl ThreadPinner for OsPinner {
+    #[cfg(target_os = "linux")]
+    fn pin_current_thread(&self, core: usize) -> bool {
+        if core >= libc::CPU_SETSIZE as usize {
+            return false;
+        }
+        // SAFETY: cpu_set_t is a plain bitmask, and pid 0 targets only the calling thread
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(core, &mut set);
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin_current_thread(&self, _core: usize) -> bool {
+        false
+    }
+}
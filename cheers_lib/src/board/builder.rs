@@ -0,0 +1,248 @@
+use std::fmt::Display;
+
+use cheers_bitboards::{BitBoard, Square};
+
+use super::Board;
+use crate::types::{Color, Piece};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BoardBuildError {
+    KingCount(Color),
+    PawnOnBackRank(Square),
+    Castling(Color),
+    EnPassant(Square),
+    OpponentInCheck,
+}
+
+impl Display for BoardBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KingCount(color) => write!(f, "{color:?} must have exactly one king"),
+            Self::PawnOnBackRank(square) => write!(f, "Pawn on back rank: {}", square.coord()),
+            Self::Castling(color) => write!(
+                f,
+                "{color:?} castling rights without king and rook on their starting squares"
+            ),
+            Self::EnPassant(square) => write!(f, "Invalid en passant square: {}", square.coord()),
+            Self::OpponentInCheck => write!(f, "Side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for BoardBuildError {}
+
+/// Sets up a position piece by piece, as an alternative to writing out a FEN.
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+    pieces: [Option<(Color, Piece)>; 64],
+    side_to_move: Color,
+    // [color][0 = kingside, 1 = queenside]
+    castling: [[bool; 2]; 2],
+    ep: Option<Square>,
+    halfmove_clock: u16,
+}
+
+impl BoardBuilder {
+    pub fn new() -> Self {
+        Self {
+            pieces: [None; 64],
+            side_to_move: Color::White,
+            castling: [[false; 2]; 2],
+            ep: None,
+            halfmove_clock: 0,
+        }
+    }
+
+    pub fn place(mut self, square: Square, color: Color, piece: Piece) -> Self {
+        self.pieces[square] = Some((color, piece));
+        self
+    }
+
+    pub fn side_to_move(mut self, color: Color) -> Self {
+        self.side_to_move = color;
+        self
+    }
+
+    // standard castling only: the king must be on the e-file and the rook in the corner
+    pub fn castling(mut self, color: Color, kingside: bool) -> Self {
+        self.castling[color][!kingside as usize] = true;
+        self
+    }
+
+    // the square a pawn skipped over with its double push
+    pub fn ep(mut self, square: Option<Square>) -> Self {
+        self.ep = square;
+        self
+    }
+
+    pub fn halfmove_clock(mut self, halfmove_clock: u16) -> Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+
+    pub fn build(&self) -> Result<Board, BoardBuildError> {
+        for color in [Color::White, Color::Black] {
+            let kings = self
+                .pieces
+                .iter()
+                .filter(|&&p| p == Some((color, Piece::King)))
+                .count();
+            if kings != 1 {
+                return Err(BoardBuildError::KingCount(color));
+            }
+        }
+
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let square = Square::from(i as u8);
+            if matches!(piece, Some((_, Piece::Pawn))) && matches!(square.rank(), 0 | 7) {
+                return Err(BoardBuildError::PawnOnBackRank(square));
+            }
+        }
+
+        let mut castling_rights = [[BitBoard::empty(); 2]; 2];
+        for (color, (king, kingside_rook, queenside_rook)) in [
+            (Color::White, (Square::E1, Square::H1, Square::A1)),
+            (Color::Black, (Square::E8, Square::H8, Square::A8)),
+        ] {
+            for (side, rook) in [kingside_rook, queenside_rook].into_iter().enumerate() {
+                if !self.castling[color][side] {
+                    continue;
+                }
+                if self.pieces[king] != Some((color, Piece::King))
+                    || self.pieces[rook] != Some((color, Piece::Rook))
+                {
+                    return Err(BoardBuildError::Castling(color));
+                }
+                castling_rights[color][side] = rook.bitboard();
+            }
+        }
+
+        let ep_mask = match self.ep {
+            Some(square) if !matches!(square.rank(), 2 | 5) => {
+                return Err(BoardBuildError::EnPassant(square))
+            }
+            Some(square) => {
+                // the pawn that just double pushed belongs to the side not to move
+                let (rank, pawn, origin, pawn_color) = if self.side_to_move == Color::White {
+                    (5, *square as usize - 8, *square as usize + 8, Color::Black)
+                } else {
+                    (2, *square as usize + 8, *square as usize - 8, Color::White)
+                };
+                if square.rank() != rank
+                    || self.pieces[square].is_some()
+                    || self.pieces[origin].is_some()
+                    || self.pieces[pawn] != Some((pawn_color, Piece::Pawn))
+                {
+                    return Err(BoardBuildError::EnPassant(square));
+                }
+                square.bitboard()
+            }
+            None => BitBoard::empty(),
+        };
+
+        let mut pieces = [[BitBoard::empty(); 6]; 2];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if let Some((color, piece)) = *piece {
+                pieces[color][piece] |= Square::from(i as u8).bitboard();
+            }
+        }
+        let board = Board::from_pieces(
+            pieces,
+            self.side_to_move == Color::Black,
+            castling_rights,
+            ep_mask,
+            self.halfmove_clock,
+        );
+        if board.illegal_position() {
+            return Err(BoardBuildError::OpponentInCheck);
+        }
+
+        Ok(board)
+    }
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::Square;
+
+    use super::{BoardBuildError, BoardBuilder};
+    use crate::{
+        board::Board,
+        types::{Color::*, Piece::*},
+    };
+
+    #[test]
+    fn test_build_startpos() {
+        let back_rank = [Rook, Knight, Bishop, Queen, King, Bishop, Knight, Rook];
+        let mut builder = BoardBuilder::new();
+        for (file, piece) in back_rank.into_iter().enumerate() {
+            builder = builder
+                .place(Square::from(file as u8), White, piece)
+                .place(Square::from(8 + file as u8), White, Pawn)
+                .place(Square::from(48 + file as u8), Black, Pawn)
+                .place(Square::from(56 + file as u8), Black, piece);
+        }
+        let board = builder
+            .castling(White, true)
+            .castling(White, false)
+            .castling(Black, true)
+            .castling(Black, false)
+            .build()
+            .unwrap();
+
+        assert_eq!(board.hash(), Board::new().hash());
+        assert_eq!(board.fen(), Board::new().fen());
+    }
+
+    #[test]
+    fn test_build_validation() {
+        let kings =
+            BoardBuilder::new()
+                .place(Square::E1, White, King)
+                .place(Square::E8, Black, King);
+
+        assert!(kings.build().is_ok());
+        assert_eq!(
+            BoardBuilder::new()
+                .place(Square::E1, White, King)
+                .build()
+                .err(),
+            Some(BoardBuildError::KingCount(Black))
+        );
+        assert_eq!(
+            kings.clone().place(Square::A8, White, Pawn).build().err(),
+            Some(BoardBuildError::PawnOnBackRank(Square::A8))
+        );
+        assert_eq!(
+            kings.clone().castling(White, true).build().err(),
+            Some(BoardBuildError::Castling(White))
+        );
+        assert_eq!(
+            kings.clone().ep(Some(Square::D6)).build().err(),
+            Some(BoardBuildError::EnPassant(Square::D6))
+        );
+        assert_eq!(
+            kings
+                .clone()
+                .place(Square::E4, White, Rook)
+                .side_to_move(White)
+                .build()
+                .err(),
+            Some(BoardBuildError::OpponentInCheck)
+        );
+
+        let ep = kings
+            .place(Square::D5, Black, Pawn)
+            .place(Square::E5, White, Pawn)
+            .ep(Some(Square::D6))
+            .build()
+            .unwrap();
+        assert_eq!(ep.fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    fmt::Display,
+    path::Path,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
 #[cfg(feature = "eval-tracing")]
 use bytemuck::{Pod, Zeroable};
 
@@ -45,11 +51,101 @@ pub struct EvalParams {
     pub passed_pawn_table: [EvalScore; 64],
 
     pub piece_tables: PieceTables,
+
+    pub tempo: EvalScore,
+
+    // how much of the endgame score to keep, out of SCALE_NORMAL, in endings that are often
+    // drawn. only the endgame half is used, indexed by the `*_SCALE` constants
+    pub endgame_scales: [EvalScore; 3],
 }
 
-#[cfg(feature = "eval-tracing")]
 impl EvalParams {
     pub const LEN: usize = std::mem::size_of::<Self>() / std::mem::size_of::<i16>();
+    // where `endgame_scales` starts in `to_array`, the linear terms are all before it
+    pub const ENDGAME_SCALES_INDEX: usize =
+        std::mem::offset_of!(Self, endgame_scales) / std::mem::size_of::<i16>();
+
+    // parses the `{params:?}` dump that `cheers_tuning` writes to best_parameters.txt: every
+    // `s!(mg, eg)` in order, which is the field order of the struct
+    pub fn parse(text: &str) -> Result<Self, EvalParamsError> {
+        let mut scores = Vec::with_capacity(Self::LEN / 2);
+        for term in text.split("s!(").skip(1) {
+            let pair = term.split(')').next().unwrap_or_default();
+            let (mg, eg) = pair
+                .split_once(',')
+                .ok_or_else(|| EvalParamsError::Parse(pair.to_string()))?;
+            let parse = |n: &str| {
+                n.trim()
+                    .parse::<i16>()
+                    .map_err(|_| EvalParamsError::Parse(pair.to_string()))
+            };
+            scores.push(EvalScore::new(parse(mg)?, parse(eg)?));
+        }
+        if scores.len() * 2 != Self::LEN {
+            return Err(EvalParamsError::Count(scores.len() * 2));
+        }
+
+        let mut params = EVAL_PARAMS;
+        // SAFETY: the params are repr(C) and made up of nothing but EvalScores
+        let slots = unsafe {
+            std::slice::from_raw_parts_mut(
+                &mut params as *mut Self as *mut EvalScore,
+                Self::LEN / 2,
+            )
+        };
+        slots.copy_from_slice(&scores);
+        Ok(params)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, EvalParamsError> {
+        let text = std::fs::read_to_string(path).map_err(|e| EvalParamsError::Io(e.to_string()))?;
+        Self::parse(&text)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvalParamsError {
+    Io(String),
+    Parse(String),
+    // the number of parameters found, instead of `EvalParams::LEN`
+    Count(usize),
+}
+
+impl Display for EvalParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read eval parameters: {e}"),
+            Self::Parse(term) => write!(f, "Invalid eval parameter: s!({term})"),
+            Self::Count(n) => write!(f, "Expected {} eval parameters, found {n}", EvalParams::LEN),
+        }
+    }
+}
+
+impl std::error::Error for EvalParamsError {}
+
+// the parameters the evaluation uses, `EVAL_PARAMS` until an `EvalFile` is loaded
+static ACTIVE_EVAL_PARAMS: AtomicPtr<EvalParams> =
+    AtomicPtr::new(&EVAL_PARAMS as *const EvalParams as *mut EvalParams);
+
+#[inline(always)]
+pub fn eval_params() -> &'static EvalParams {
+    // SAFETY: the pointer is always to EVAL_PARAMS or to params leaked by `set_eval_params`
+    unsafe { &*ACTIVE_EVAL_PARAMS.load(Ordering::Acquire) }
+}
+
+// swap in new evaluation parameters. searches still running may hold the old ones, so they are
+// leaked rather than freed, which is fine for the handful of times a GUI loads an eval file
+pub fn set_eval_params(params: EvalParams) {
+    let params = if params == EVAL_PARAMS {
+        &EVAL_PARAMS as *const EvalParams as *mut EvalParams
+    } else {
+        Box::leak(Box::new(params)) as *mut EvalParams
+    };
+    ACTIVE_EVAL_PARAMS.store(params, Ordering::Release);
+}
+
+#[cfg(feature = "eval-tracing")]
+impl EvalParams {
     pub fn to_array(&mut self) -> [i16; Self::LEN] {
         let array = &mut bytemuck::cast::<EvalParams, [i16; Self::LEN]>(*self);
         array
@@ -124,7 +220,13 @@ pub struct EvalTrace {
     pub queen_placement: [[i16; 2]; 64],
     pub king_placement: [[i16; 2]; 64],
 
+    pub tempo: [i16; 2],
+
+    // 1 for the side whose endgame score was scaled, in the slot of the scale used
+    pub endgame_scales: [[i16; 2]; 3],
+
     pub turn: i16,
+    pub eg_scale: i16,
 }
 
 #[cfg(feature = "eval-tracing")]
@@ -145,9 +247,18 @@ impl Default for EvalTrace {
     }
 }
 
-// static assert that eval params and trace are the same length (plus 1 for turn in trace)
+// lets the engine trace an eval for debugging without pulling in bytemuck
+#[cfg(not(feature = "eval-tracing"))]
+impl Default for EvalTrace {
+    fn default() -> Self {
+        // SAFETY: the trace only holds i16 counters, so all zeroes is a valid value
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+// static assert that eval params and trace are the same length (plus turn and eg_scale in trace)
 #[cfg(feature = "eval-tracing")]
-const _PARAMS_TRACE_LEN_EQ: () = if EvalParams::LEN + 1 == EvalTrace::LEN {
+const _PARAMS_TRACE_LEN_EQ: () = if EvalParams::LEN + 2 == EvalTrace::LEN {
     
 } else {
     panic!("Eval parameters and trace are not equal length!")
@@ -157,6 +268,12 @@ pub const CHECKMATE_SCORE: i16 = 31000;
 pub const TB_WIN_SCORE: i16 = 30000;
 pub const DRAW_SCORE: i16 = 0;
 
+// endgame scale factors are out of SCALE_NORMAL. the rest index `EvalParams::endgame_scales`
+pub const SCALE_NORMAL: i32 = 128;
+pub const DRAWN_ENDING_SCALE: usize = 0;
+pub const OPPOSITE_BISHOPS_SCALE: usize = 1;
+pub const ROOK_ENDING_ONE_WING_SCALE: usize = 2;
+
 pub static EVAL_PARAMS: EvalParams = EvalParams {
     piece_values: PieceValues([
         s!(42, 110),
@@ -801,4 +918,11 @@ pub static EVAL_PARAMS: EvalParams = EvalParams {
             s!(75, -78),
         ],
     ]),
+    // untuned, so it stays neutral until cheers_tuning has fitted it
+    tempo: s!(0, 0),
+    endgame_scales: [
+        s!(0, 0),
+        s!(0, 64),
+        s!(0, 96),
+    ],
 };
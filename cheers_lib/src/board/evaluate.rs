@@ -5,9 +5,17 @@ use Piece::*;
 use super::Board;
 pub use super::{eval_params::*, eval_types::*};
 
+// blend the midgame and endgame scores by the game phase, from 0 in the opening to 256 with
+// only kings and pawns left
+pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    (mg * (256 - phase) + eg * phase) / 256
+}
+
 pub struct EvalContext<'search, T> {
     game: &'search Board,
     trace: &'search mut T,
+    // loaded once per eval, so an `EvalFile` swapped in mid-eval can't mix two sets of params
+    params: &'static EvalParams,
 }
 
 impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
@@ -15,7 +23,30 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         self.trace.term(|t| t.turn = black as i16);
 
         let phase = self.game.game_phase();
+        let eval = self.evaluate_score(pawn_cache);
+
+        let white_winning = eval.eg() >= 0;
+        let scale = match self.game.endgame_scale_kind(white_winning) {
+            Some(kind) => {
+                self.trace
+                    .term(|t| t.endgame_scales[kind][!white_winning as usize] = 1);
+                self.params.endgame_scales[kind].eg() as i32
+            }
+            None => SCALE_NORMAL,
+        };
+        self.trace.term(|t| t.eg_scale = scale as i16);
+        let eg = eval.eg() as i32 * scale / SCALE_NORMAL;
 
+        let final_eval = taper(eval.mg() as i32, eg, phase) as i16;
+        if black {
+            -final_eval
+        } else {
+            final_eval
+        }
+    }
+
+    // the midgame and endgame scores from white's perspective, before phase interpolation
+    pub fn evaluate_score(&mut self, pawn_cache: &mut PawnHashTable) -> EvalScore {
         let white_king_square = self.game.white_king.first_square();
         let black_king_square = self.game.black_king.first_square();
 
@@ -30,26 +61,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 passers & self.game.black_pawns,
             )
         } else {
-            let front_spans_black = Board::pawn_front_spans::<Black>(self.game.black_pawns);
-            let all_front_spans_black = front_spans_black
-                | (front_spans_black & NOT_H_FILE) << 1
-                | (front_spans_black & NOT_A_FILE) >> 1;
-            let rear_spans_black = Board::pawn_push_spans::<White>(self.game.black_pawns);
-
-            let front_spans_white = Board::pawn_front_spans::<White>(self.game.white_pawns);
-            let all_front_spans_white = front_spans_white
-                | (front_spans_white & NOT_H_FILE) << 1
-                | (front_spans_white & NOT_A_FILE) >> 1;
-            let rear_spans_white = Board::pawn_push_spans::<Black>(self.game.white_pawns);
-
-            let white_passers = self.game.white_pawns
-                & all_front_spans_black.inverse()
-                & rear_spans_white.inverse();
-            let black_passers = self.game.black_pawns
-                & all_front_spans_white.inverse()
-                & rear_spans_black.inverse();
-
-            (white_passers, black_passers)
+            self.passed_pawns()
         };
 
         // initialise eval info
@@ -72,6 +84,19 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         let mut eval = EvalScore::zero();
 
         if let Some((white_score, _)) = pawn_cache_entry {
+            // recompute the cached terms to catch pawn hash collisions and bugs in the
+            // incremental pawn hash. Skipped when tracing so the pawn terms aren't counted twice
+            #[cfg(feature = "paranoid")]
+            if !T::TRACING {
+                let recomputed =
+                    self.evaluate_pawns_only::<White>() - self.evaluate_pawns_only::<Black>();
+                debug_assert!(
+                    recomputed == white_score
+                        && self.passed_pawns() == (white_passers, black_passers),
+                    "Pawn hash mismatch in {}",
+                    self.game.fen()
+                );
+            }
             eval += white_score;
         } else {
             let white_score =
@@ -99,18 +124,44 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         eval += self.evaluate_king::<White>(&info) - self.evaluate_king::<Black>(&info);
 
+        // bonus for the side to move
+        eval += if self.game.black_to_move {
+            -self.params.tempo
+        } else {
+            self.params.tempo
+        };
+        self.trace
+            .term(|t| t.tempo[self.game.black_to_move as usize] += 1);
+
         // scale down evals for material draws
         if self.game.material_draw() {
             eval.div_by(32);
         }
 
-        let final_eval =
-            (((eval.mg() as i32 * (256 - phase)) + (eval.eg() as i32 * phase)) / 256) as i16;
-        if black {
-            -final_eval
-        } else {
-            final_eval
-        }
+        eval
+    }
+
+    // (white, black) pawns with no enemy pawns in front of them or on the adjacent files, and
+    // no friendly pawns in front
+    fn passed_pawns(&self) -> (BitBoard, BitBoard) {
+        let front_spans_black = Board::pawn_front_spans::<Black>(self.game.black_pawns);
+        let all_front_spans_black = front_spans_black
+            | (front_spans_black & NOT_H_FILE) << 1
+            | (front_spans_black & NOT_A_FILE) >> 1;
+        let rear_spans_black = Board::pawn_push_spans::<White>(self.game.black_pawns);
+
+        let front_spans_white = Board::pawn_front_spans::<White>(self.game.white_pawns);
+        let all_front_spans_white = front_spans_white
+            | (front_spans_white & NOT_H_FILE) << 1
+            | (front_spans_white & NOT_A_FILE) >> 1;
+        let rear_spans_white = Board::pawn_push_spans::<Black>(self.game.white_pawns);
+
+        let white_passers =
+            self.game.white_pawns & all_front_spans_black.inverse() & rear_spans_white.inverse();
+        let black_passers =
+            self.game.black_pawns & all_front_spans_white.inverse() & rear_spans_black.inverse();
+
+        (white_passers, black_passers)
     }
 
     pub fn evaluate_knights<W: TypeColor>(&mut self, info: &EvalInfo) -> EvalScore {
@@ -126,7 +177,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         // material value
         let count = knights.count_ones() as i16;
-        eval += EVAL_PARAMS.piece_values[Knight] * count;
+        eval += self.params.piece_values[Knight] * count;
         self.trace.term(|t| t.knight_count[color] = count);
 
         // knights behind pawns
@@ -136,21 +187,21 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             self.game.black_pawns << 8
         };
         let knights_behind_pawns = (knights & behind_pawns).count_ones() as i16;
-        eval += EVAL_PARAMS.knight_behind_pawn * knights_behind_pawns;
+        eval += self.params.knight_behind_pawn * knights_behind_pawns;
         self.trace
             .term(|t| t.knight_beind_pawn[color] += knights_behind_pawns);
 
         for knight in knights {
             let relative_knight = relative_board_index::<W>(knight);
             // placement
-            eval += EVAL_PARAMS.piece_tables[(Knight, relative_knight)];
+            eval += self.params.piece_tables[(Knight, relative_knight)];
             self.trace
                 .term(|t| t.knight_placement[relative_knight][color] += 1);
 
             // mobility
             let mobility =
                 (lookup_knight(knight) & info.mobility_area[color]).count_ones() as usize;
-            eval += EVAL_PARAMS.knight_mobility[mobility];
+            eval += self.params.knight_mobility[mobility];
             self.trace.term(|t| t.knight_mobility[mobility][color] += 1);
 
             // outposts
@@ -164,7 +215,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             let defended = (Board::pawn_attack::<W::Other>(knight) & pawns).is_not_empty() as usize;
             // normal - 0, outpost - 1, defended outpost - 2
             let outpost_score = outpost + defended * outpost;
-            eval += EVAL_PARAMS.knight_outpost[outpost_score];
+            eval += self.params.knight_outpost[outpost_score];
             self.trace
                 .term(|t| t.knight_outpost[outpost_score][color] += 1);
 
@@ -176,7 +227,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 .enumerate()
                 .for_each(|(i, &p)| {
                     let threats = (p & attacks).count_ones() as i16;
-                    eval += EVAL_PARAMS.knight_threats[i] * threats;
+                    eval += self.params.knight_threats[i] * threats;
                     self.trace.term(|t| t.knight_threats[i][color] += threats)
                 });
         }
@@ -196,7 +247,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         // material value
         let count = bishops.count_ones() as i16;
-        eval += EVAL_PARAMS.piece_values[Bishop] * count;
+        eval += self.params.piece_values[Bishop] * count;
         self.trace.term(|t| t.bishop_count[color] = count);
 
         // bishops behind pawns
@@ -206,26 +257,26 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             self.game.black_pawns << 8
         };
         let bishops_behind_pawns = (bishops & behind_pawns).count_ones() as i16;
-        eval += EVAL_PARAMS.bishop_behind_pawn * bishops_behind_pawns;
+        eval += self.params.bishop_behind_pawn * bishops_behind_pawns;
         self.trace
             .term(|t| t.bishop_beind_pawn[color] += bishops_behind_pawns);
 
         // bishop pair
         let bishop_pair = (bishops.count_ones() > 1) as i16;
-        eval += EVAL_PARAMS.bishop_pair * bishop_pair;
+        eval += self.params.bishop_pair * bishop_pair;
         self.trace.term(|t| t.bishop_pair[color] += bishop_pair);
 
         for bishop in bishops {
             // placement
             let relative_bishop = relative_board_index::<W>(bishop);
-            eval += EVAL_PARAMS.piece_tables[(Bishop, relative_bishop)];
+            eval += self.params.piece_tables[(Bishop, relative_bishop)];
             self.trace
                 .term(|t| t.bishop_placement[relative_bishop][color] += 1);
 
             // mobility
             let mobility = (lookup_bishop(bishop, self.game.occupied) & info.mobility_area[color])
                 .count_ones() as usize;
-            eval += EVAL_PARAMS.bishop_mobility[mobility];
+            eval += self.params.bishop_mobility[mobility];
             self.trace.term(|t| t.bishop_mobility[mobility][color] += 1);
 
             // outposts
@@ -239,7 +290,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             let defended = (Board::pawn_attack::<W::Other>(bishop) & pawns).is_not_empty() as usize;
             // normal - 0, outpost - 1, defended outpost - 2
             let outpost_score = outpost + defended * outpost;
-            eval += EVAL_PARAMS.bishop_outpost[outpost_score];
+            eval += self.params.bishop_outpost[outpost_score];
             self.trace
                 .term(|t| t.bishop_outpost[outpost_score][color] += 1);
 
@@ -251,7 +302,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 .enumerate()
                 .for_each(|(i, &p)| {
                     let threats = (p & attacks).count_ones() as i16;
-                    eval += EVAL_PARAMS.bishop_threats[i] * threats;
+                    eval += self.params.bishop_threats[i] * threats;
                     self.trace.term(|t| t.bishop_threats[i][color] += threats)
                 });
         }
@@ -271,20 +322,20 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         // material value
         let count = rooks.count_ones() as i16;
-        eval += EVAL_PARAMS.piece_values[Rook] * count;
+        eval += self.params.piece_values[Rook] * count;
         self.trace.term(|t| t.rook_count[color] = count);
 
         for rook in rooks {
             // placement
             let relative_rook = relative_board_index::<W>(rook);
-            eval += EVAL_PARAMS.piece_tables[(Rook, relative_rook)];
+            eval += self.params.piece_tables[(Rook, relative_rook)];
             self.trace
                 .term(|t| t.rook_placement[relative_rook][color] += 1);
 
             // mobility
             let mobility = (lookup_rook(rook, self.game.occupied) & info.mobility_area[color])
                 .count_ones() as usize;
-            eval += EVAL_PARAMS.rook_mobility[mobility];
+            eval += self.params.rook_mobility[mobility];
             self.trace.term(|t| t.rook_mobility[mobility][color] += 1);
 
             // open files
@@ -298,14 +349,14 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             let open = (FILES[rook.file()] & enemy_pawns).is_empty() as usize;
             // normal - 0, semi-open - 1, open - 2
             let open_score = semi_open + semi_open * open;
-            eval += EVAL_PARAMS.rook_on_open_file[open_score];
+            eval += self.params.rook_on_open_file[open_score];
             self.trace
                 .term(|t| t.rook_on_open_file[open_score][color] += 1);
 
             // on a file with any queen
             let queens = self.game.white_queens | self.game.black_queens;
             let queen_file = (FILES[rook.file()] & queens).is_not_empty() as i16;
-            eval += EVAL_PARAMS.rook_queen_file * queen_file;
+            eval += self.params.rook_queen_file * queen_file;
             self.trace.term(|t| t.rook_queen_file[color] += queen_file);
 
             // threats
@@ -316,7 +367,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 .enumerate()
                 .for_each(|(i, &p)| {
                     let threats = (p & attacks).count_ones() as i16;
-                    eval += EVAL_PARAMS.rook_threats[i] * threats;
+                    eval += self.params.rook_threats[i] * threats;
                     self.trace.term(|t| t.rook_threats[i][color] += threats)
                 });
         }
@@ -334,25 +385,25 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         let color = W::INDEX;
         // material value
         let count = queens.count_ones() as i16;
-        eval += EVAL_PARAMS.piece_values[Queen] * count;
+        eval += self.params.piece_values[Queen] * count;
         self.trace.term(|t| t.queen_count[color] = count);
 
         for queen in queens {
             // placement
             let relative_queen = relative_board_index::<W>(queen);
-            eval += EVAL_PARAMS.piece_tables[(Queen, relative_queen)];
+            eval += self.params.piece_tables[(Queen, relative_queen)];
             self.trace
                 .term(|t| t.queen_placement[relative_queen][color] += 1);
 
             // mobility
             let mobility = (lookup_queen(queen, self.game.occupied) & info.mobility_area[color])
                 .count_ones() as usize;
-            eval += EVAL_PARAMS.queen_mobility[mobility];
+            eval += self.params.queen_mobility[mobility];
             self.trace.term(|t| t.queen_mobility[mobility][color] += 1);
 
             // discovery risk
             let discoveries = self.game.discovered_attacks::<W>(queen).is_not_empty() as i16;
-            eval += EVAL_PARAMS.queen_discovery_risk * discoveries;
+            eval += self.params.queen_discovery_risk * discoveries;
             self.trace
                 .term(|t| t.queen_discovery_risk[color] += discoveries);
         }
@@ -367,7 +418,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         // placement
         let relative_king = relative_board_index::<W>(info.king_square[color]);
-        eval += EVAL_PARAMS.piece_tables[(King, relative_king)];
+        eval += self.params.piece_tables[(King, relative_king)];
         self.trace
             .term(|t| t.king_placement[relative_king][color] += 1);
 
@@ -380,7 +431,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         let semi_open = (FILES[king.file()] & pawns).is_empty() as usize;
         let open = (FILES[king.file()] & enemy_pawns).is_empty() as usize;
 
-        eval += EVAL_PARAMS.king_on_open_file[semi_open + semi_open * open];
+        eval += self.params.king_on_open_file[semi_open + semi_open * open];
         self.trace
             .term(|t| t.king_on_open_file[semi_open + semi_open * open][color] += 1);
 
@@ -393,20 +444,20 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 & info.king_area[color])
                 .count_ones())
         .min(15) as usize;
-        eval += EVAL_PARAMS.king_ring_attacks[king_ring_attacks];
+        eval += self.params.king_ring_attacks[king_ring_attacks];
         self.trace
             .term(|t| t.king_ring_attacks[king_ring_attacks][color] += 1);
 
         // king virtual mobility
         let mobility = (lookup_queen(king, self.game.occupied) & info.mobility_area[color])
             .count_ones() as usize;
-        eval += EVAL_PARAMS.king_virtual_mobility[mobility];
+        eval += self.params.king_virtual_mobility[mobility];
         self.trace
             .term(|t| t.king_virtual_mobility[mobility][color] += 1);
 
         // discovery risk
         let discoveries = self.game.discovered_attacks::<W>(king).is_not_empty() as i16;
-        eval += EVAL_PARAMS.king_discovery_risk * discoveries;
+        eval += self.params.king_discovery_risk * discoveries;
         self.trace
             .term(|t| t.king_discovery_risk[color] += discoveries);
 
@@ -426,13 +477,13 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
 
         // material value
         let count = pawns.count_ones() as i16;
-        eval += EVAL_PARAMS.piece_values[Pawn] * count;
+        eval += self.params.piece_values[Pawn] * count;
         self.trace.term(|t| t.pawn_count[color] = count);
 
         // doubled pawns per-file
         for file in FILES {
             let file_double_pawn_count = (pawns & file).count_ones().min(4) as usize;
-            eval += EVAL_PARAMS.pawn_doubled[file_double_pawn_count];
+            eval += self.params.pawn_doubled[file_double_pawn_count];
             self.trace
                 .term(|t| t.pawn_doubled[file_double_pawn_count][color] += 1);
         }
@@ -440,14 +491,14 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         for pawn in pawns {
             // placement
             let relative_pawn = relative_board_index::<W>(pawn);
-            eval += EVAL_PARAMS.piece_tables[(Pawn, relative_pawn)];
+            eval += self.params.piece_tables[(Pawn, relative_pawn)];
             self.trace
                 .term(|t| t.pawn_placement[relative_pawn][color] += 1);
 
             // connected
             let connected_pawns =
                 (Board::pawn_attack::<W::Other>(pawn) & pawns).count_ones() as usize;
-            eval += EVAL_PARAMS.pawn_connected[connected_pawns];
+            eval += self.params.pawn_connected[connected_pawns];
             self.trace
                 .term(|t| t.pawn_connected[connected_pawns][color] += 1);
 
@@ -457,13 +508,13 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 & pawns)
                 .count_ones() as usize;
 
-            eval += EVAL_PARAMS.pawn_phalanx[phalanx_pawns];
+            eval += self.params.pawn_phalanx[phalanx_pawns];
             self.trace
                 .term(|t| t.pawn_phalanx[phalanx_pawns][color] += 1);
 
             // isolated
             let pawn_isolated = (pawns & adjacent_files(pawn.file())).is_empty() as usize;
-            eval += EVAL_PARAMS.pawn_isolated[pawn_isolated];
+            eval += self.params.pawn_isolated[pawn_isolated];
             self.trace
                 .term(|t| t.pawn_isolated[pawn_isolated][color] += 1);
 
@@ -475,7 +526,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             } & other_pawns;
             let backward = (self.game.pawn_adjacent_rear_span::<W>(pawn) & pawns).is_empty()
                 && gatekeeper.is_not_empty();
-            eval += EVAL_PARAMS.pawn_backward[backward as usize];
+            eval += self.params.pawn_backward[backward as usize];
             self.trace
                 .term(|t| t.pawn_backward[backward as usize][color] += 1)
         }
@@ -496,7 +547,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
             .enumerate()
             .for_each(|(i, &p)| {
                 let threats = (p & attacks).count_ones() as i16;
-                eval += EVAL_PARAMS.pawn_threats[i] * threats;
+                eval += self.params.pawn_threats[i] * threats;
                 self.trace.term(|t| t.pawn_threats[i][color] += threats)
             });
 
@@ -505,7 +556,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
         for passer in passers {
             // placement
             let relative_passer = relative_board_index::<W>(passer);
-            eval += EVAL_PARAMS.passed_pawn_table[relative_passer];
+            eval += self.params.passed_pawn_table[relative_passer];
             self.trace
                 .term(|t| t.passed_pawn_placement[relative_passer][color] += 1);
 
@@ -517,7 +568,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 .max(passer.file().abs_diff(king.file()))
                 .min(4)
                 - 1;
-            eval += EVAL_PARAMS.passed_pawn_friendly_king_distance[friendly_distance];
+            eval += self.params.passed_pawn_friendly_king_distance[friendly_distance];
             self.trace
                 .term(|t| t.passed_pawn_friendly_king_distance[friendly_distance][color] += 1);
 
@@ -528,7 +579,7 @@ impl<'search, T: TraceTarget + Default> EvalContext<'search, T> {
                 .max(passer.file().abs_diff(other_king.file()))
                 .min(4)
                 - 1;
-            eval += EVAL_PARAMS.passed_pawn_enemy_king_distance[enemy_distance];
+            eval += self.params.passed_pawn_enemy_king_distance[enemy_distance];
             self.trace
                 .term(|t| t.passed_pawn_enemy_king_distance[enemy_distance][color] += 1);
         }
@@ -561,12 +612,185 @@ impl Board {
         let mut eval = EvalContext {
             game: self,
             trace: &mut trace,
+            params: eval_params(),
         };
         let score = eval.evaluate(pawn_hash_table, self.black_to_move);
 
         (score, trace)
     }
 
+    // white-relative eval split into its midgame and endgame halves, with the game phase and
+    // the endgame scale factor that is applied to the endgame half
+    pub fn evaluate_breakdown(&self, pawn_hash_table: &mut PawnHashTable) -> (EvalScore, i32, i32) {
+        let mut trace = ();
+        let mut eval = EvalContext {
+            game: self,
+            trace: &mut trace,
+            params: eval_params(),
+        };
+
+        let score = eval.evaluate_score(pawn_hash_table);
+        let scale = self.endgame_scale(score.eg() >= 0);
+        (score, self.game_phase(), scale)
+    }
+
+    // white-relative contributions of the major groups of eval terms, before the phase blend.
+    // 'other' is whatever the groups don't cover, so the terms add up to 'total'
+    pub fn eval_terms(&self) -> [(&'static str, EvalScore); 7] {
+        fn sum(params: &[EvalScore], trace: &[[i16; 2]]) -> EvalScore {
+            params
+                .iter()
+                .zip(trace)
+                .fold(EvalScore::zero(), |acc, (&p, t)| acc + p * (t[0] - t[1]))
+        }
+
+        let mut trace = EvalTrace::default();
+        let mut eval = EvalContext {
+            game: self,
+            trace: &mut trace,
+            params: eval_params(),
+        };
+        let total = eval.evaluate_score(&mut PawnHashTable::new());
+        let p = eval_params();
+        let t = &trace;
+
+        let material = sum(
+            &p.piece_values.0,
+            &[
+                t.pawn_count,
+                t.knight_count,
+                t.bishop_count,
+                t.rook_count,
+                t.queen_count,
+                t.king_count,
+            ],
+        );
+        let psqt = [
+            &t.pawn_placement,
+            &t.knight_placement,
+            &t.bishop_placement,
+            &t.rook_placement,
+            &t.queen_placement,
+            &t.king_placement,
+        ]
+        .into_iter()
+        .zip(&p.piece_tables.0)
+        .fold(EvalScore::zero(), |acc, (t, p)| acc + sum(p, t));
+        let pawns = sum(&p.pawn_connected, &t.pawn_connected)
+            + sum(&p.pawn_phalanx, &t.pawn_phalanx)
+            + sum(&p.pawn_isolated, &t.pawn_isolated)
+            + sum(&p.pawn_doubled, &t.pawn_doubled)
+            + sum(&p.pawn_backward, &t.pawn_backward)
+            + sum(
+                &p.passed_pawn_friendly_king_distance,
+                &t.passed_pawn_friendly_king_distance,
+            )
+            + sum(
+                &p.passed_pawn_enemy_king_distance,
+                &t.passed_pawn_enemy_king_distance,
+            )
+            + sum(&p.passed_pawn_table, &t.passed_pawn_placement);
+        let mobility = sum(&p.knight_mobility, &t.knight_mobility)
+            + sum(&p.bishop_mobility, &t.bishop_mobility)
+            + sum(&p.rook_mobility, &t.rook_mobility)
+            + sum(&p.queen_mobility, &t.queen_mobility);
+        let king_safety = sum(&p.king_on_open_file, &t.king_on_open_file)
+            + sum(&p.king_ring_attacks, &t.king_ring_attacks)
+            + sum(&p.king_virtual_mobility, &t.king_virtual_mobility)
+            + sum(&[p.king_discovery_risk], &[t.king_discovery_risk]);
+        let other = total - material - psqt - pawns - mobility - king_safety;
+
+        [
+            ("material", material),
+            ("psqt", psqt),
+            ("pawns", pawns),
+            ("mobility", mobility),
+            ("king_safety", king_safety),
+            ("other", other),
+            ("total", total),
+        ]
+    }
+
+    // how much of the endgame score to keep, out of SCALE_NORMAL, for endings that
+    // are often drawn despite a material edge
+    pub fn endgame_scale(&self, white_winning: bool) -> i32 {
+        self.endgame_scale_kind(white_winning)
+            .map_or(SCALE_NORMAL, |kind| {
+                eval_params().endgame_scales[kind].eg() as i32
+            })
+    }
+
+    // which of `EvalParams::endgame_scales` applies, if any
+    pub fn endgame_scale_kind(&self, white_winning: bool) -> Option<usize> {
+        if white_winning {
+            self.endgame_scale_kind_for::<White>()
+        } else {
+            self.endgame_scale_kind_for::<Black>()
+        }
+    }
+
+    fn endgame_scale_kind_for<S: TypeColor>(&self) -> Option<usize> {
+        let strong = self.pieces::<S>();
+        let weak = self.pieces::<S::Other>();
+        let knights = strong[Knight] | weak[Knight];
+        let bishops = strong[Bishop] | weak[Bishop];
+        let rooks = strong[Rook] | weak[Rook];
+        let queens = strong[Queen] | weak[Queen];
+
+        // rook pawns with no help, or only a bishop that can't cover the queening square,
+        // can't win once the defending king reaches the corner
+        if (knights | rooks | queens | weak[Pawn] | weak[Bishop]).is_empty()
+            && strong[Bishop].count_ones() <= 1
+            && strong[Pawn].is_not_empty()
+        {
+            for file in [A_FILE, H_FILE] {
+                if (strong[Pawn] & file.inverse()).is_not_empty() {
+                    continue;
+                }
+                let queening_square =
+                    (file & if S::WHITE { EIGHTH_RANK } else { FIRST_RANK }).first_square();
+                let queening_shade = if (queening_square.bitboard() & LIGHT_SQUARES).is_empty() {
+                    DARK_SQUARES
+                } else {
+                    LIGHT_SQUARES
+                };
+                let wrong_bishop = (strong[Bishop] & queening_shade).is_empty();
+                let defender_in_corner =
+                    ((queening_square.bitboard() | lookup_king(queening_square)) & weak[King])
+                        .is_not_empty();
+                if wrong_bishop && defender_in_corner {
+                    return Some(DRAWN_ENDING_SCALE);
+                }
+            }
+        }
+
+        // a single pair of opposite coloured bishops
+        if (knights | rooks | queens).is_empty()
+            && strong[Bishop].count_ones() == 1
+            && weak[Bishop].count_ones() == 1
+            && (bishops & LIGHT_SQUARES).count_ones() == 1
+        {
+            return Some(OPPOSITE_BISHOPS_SCALE);
+        }
+
+        // one rook each with every pawn on the same wing and at most one pawn up
+        let pawns = strong[Pawn] | weak[Pawn];
+        let queenside = A_FILE | B_FILE | C_FILE | D_FILE;
+        if (knights | bishops | queens).is_empty()
+            && strong[Rook].count_ones() == 1
+            && weak[Rook].count_ones() == 1
+            && ((pawns & queenside).is_empty() || (pawns & queenside.inverse()).is_empty())
+            && strong[Pawn].count_ones() <= weak[Pawn].count_ones() + 1
+        {
+            return Some(ROOK_ENDING_ONE_WING_SCALE);
+        }
+
+        None
+    }
+
+    // how far the game is into the endgame, from the piece material left on the board: 0 with
+    // all the starting pieces (or more, after promotions), up to 256 with only kings and pawns.
+    // this is the `phase` that `taper` blends midgame and endgame scores with
     pub fn game_phase(&self) -> i32 {
         let knight_phase = 1;
         let bishop_phase = 1;
@@ -582,7 +806,7 @@ impl Board {
         phase += (self.white_rooks | self.black_rooks).count_ones() as i32 * rook_phase;
         phase += (self.white_queens | self.black_queens).count_ones() as i32 * queen_phase;
 
-        (256 * (total_phase.saturating_sub(phase))) / total_phase
+        (256 * (total_phase - phase).max(0)) / total_phase
     }
 }
 
@@ -594,3 +818,199 @@ pub fn relative_board_index<W: TypeColor>(i: Square) -> Square {
         (*i ^ 56).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endgame_scale() {
+        for (fen, white_winning, scale) in [
+            // opposite and same coloured bishops
+            (
+                "4k3/5p2/4b3/8/8/2B5/5PP1/4K3 w - - 0 1",
+                true,
+                Some(OPPOSITE_BISHOPS_SCALE),
+            ),
+            ("4k3/5p2/3b4/8/8/2B5/5PP1/4K3 w - - 0 1", true, None),
+            // rook pawns with the defending king in the corner or too far away
+            (
+                "k7/8/8/8/P7/8/8/4K3 w - - 0 1",
+                true,
+                Some(DRAWN_ENDING_SCALE),
+            ),
+            ("7k/8/8/8/P7/8/8/4K3 w - - 0 1", true, None),
+            (
+                "4k3/8/8/8/8/8/7p/6K1 b - - 0 1",
+                false,
+                Some(DRAWN_ENDING_SCALE),
+            ),
+            // wrong and right coloured bishops for the a8 queening square
+            (
+                "1k6/8/8/8/P7/8/8/2B1K3 w - - 0 1",
+                true,
+                Some(DRAWN_ENDING_SCALE),
+            ),
+            ("1k6/8/8/8/P7/8/8/3BK3 w - - 0 1", true, None),
+            // rook endings with pawns on one or both wings
+            (
+                "r3k3/5pp1/8/8/8/8/5PPP/4K2R w - - 0 1",
+                true,
+                Some(ROOK_ENDING_ONE_WING_SCALE),
+            ),
+            ("r3k3/5pp1/8/8/8/8/3P1PPP/4K2R w - - 0 1", true, None),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.endgame_scale_kind(white_winning), scale, "{fen}");
+            let expected = scale.map_or(SCALE_NORMAL, |kind| {
+                eval_params().endgame_scales[kind].eg() as i32
+            });
+            assert_eq!(board.endgame_scale(white_winning), expected, "{fen}");
+        }
+
+        // the trace marks which scale was used, for the tuner to adjust
+        let board = Board::from_fen("4k3/5p2/4b3/8/8/2B5/5PP1/4K3 w - - 0 1").unwrap();
+        let (_, trace) = board.evaluate_impl::<EvalTrace>(&mut PawnHashTable::new());
+        assert_eq!(trace.endgame_scales, [[0, 0], [1, 0], [0, 0]]);
+    }
+
+    #[test]
+    fn test_taper() {
+        let score = s!(100, -60);
+        let (mg, eg) = (score.mg() as i32, score.eg() as i32);
+        assert_eq!(taper(mg, eg, 0), 100);
+        assert_eq!(taper(mg, eg, 256), -60);
+        assert_eq!(taper(mg, eg, 128), 20);
+        assert_eq!(taper(mg, eg, 64), 60);
+
+        assert_eq!(Board::new().game_phase(), 0);
+        assert_eq!(
+            Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1")
+                .unwrap()
+                .game_phase(),
+            256
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+                .unwrap()
+                .game_phase(),
+            256
+        );
+        // promoted pieces don't take the phase below 0
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/QPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .unwrap()
+                .game_phase(),
+            0
+        );
+        // a lone queen each is most of the way to an endgame
+        assert_eq!(
+            Board::from_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1")
+                .unwrap()
+                .game_phase(),
+            256 * 16 / 24
+        );
+    }
+
+    #[test]
+    fn test_eval_terms() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let terms = board.eval_terms();
+        let (_, total) = terms[terms.len() - 1];
+        let parts = terms[..terms.len() - 1]
+            .iter()
+            .fold(EvalScore::zero(), |acc, &(_, score)| acc + score);
+        assert_eq!(parts, total);
+
+        let (score, _, _) = board.evaluate_breakdown(&mut PawnHashTable::new());
+        assert_eq!(total, score);
+
+        let pawn_up = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            pawn_up.eval_terms()[0],
+            ("material", eval_params().piece_values[Pawn])
+        );
+    }
+
+    #[test]
+    fn test_eval_symmetry() {
+        let mut board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let tempo = taper(
+            eval_params().tempo.mg() as i32,
+            eval_params().tempo.eg() as i32,
+            board.game_phase(),
+        );
+
+        let eval = board.evaluate(&mut PawnHashTable::new()) as i32;
+        board.make_null_move();
+        let null_eval = board.evaluate(&mut PawnHashTable::new()) as i32;
+
+        // each side gets the tempo bonus on its own move, allowing for rounding in the blend
+        assert!(
+            (eval + null_eval - 2 * tempo).abs() <= 2,
+            "{eval} and {null_eval} differ by more than the tempo bonus {tempo}"
+        );
+    }
+
+    #[test]
+    fn test_eval_params_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("cheers_eval_params_{}.txt", std::process::id()));
+        std::fs::write(&path, format!("{EVAL_PARAMS:?}")).unwrap();
+        let loaded = EvalParams::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, EVAL_PARAMS);
+
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let before = board.evaluate(&mut PawnHashTable::new());
+        set_eval_params(loaded);
+        assert_eq!(board.evaluate(&mut PawnHashTable::new()), before);
+
+        // edited values are picked up, and a missing or broken value is an error
+        let text = format!("{EVAL_PARAMS:?}");
+        let tempo = format!("tempo: {:?}", EVAL_PARAMS.tempo);
+        let edited = EvalParams::parse(&text.replace(&tempo, "tempo: s!(-7, 3)")).unwrap();
+        assert_eq!((edited.tempo.mg(), edited.tempo.eg()), (-7, 3));
+        assert_eq!(
+            EvalParams::parse(&text.replace(&tempo, "")),
+            Err(EvalParamsError::Count(EvalParams::LEN - 2))
+        );
+        assert!(matches!(
+            EvalParams::parse(&text.replace(&tempo, "tempo: s!(1, x)")),
+            Err(EvalParamsError::Parse(_))
+        ));
+        assert!(matches!(
+            EvalParams::from_file(std::env::temp_dir().join("cheers_no_such_file.txt")),
+            Err(EvalParamsError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_pawn_cache_consistency() {
+        use crate::moves::Move;
+
+        // pushes, captures of and by pawns, and an en passant capture
+        let moves = [
+            "e2e4", "d7d5", "e4d5", "c7c6", "d5c6", "b8c6", "h2h4", "g7g5", "h4g5", "f7f5", "g5f6",
+            "e7f6",
+        ];
+        let mut board = Board::new();
+        let mut cache = PawnHashTable::new();
+        for pair in moves {
+            board.make_move(Move::from_pair(&board, pair));
+            let fresh = Board::from_fen(board.fen()).unwrap();
+            assert_eq!(board.pawn_hash(), fresh.pawn_hash(), "{pair}");
+
+            // the first call fills the cache, the second reads it back
+            let expected = fresh.evaluate(&mut PawnHashTable::new());
+            assert_eq!(board.evaluate(&mut cache), expected, "{pair}");
+            assert_eq!(board.evaluate(&mut cache), expected, "{pair}");
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod builder;
 pub mod eval_params;
 pub mod eval_types;
 pub mod evaluate;
@@ -5,6 +6,7 @@ pub mod movegen;
 pub mod see;
 pub mod tb_adapter;
 
+use std::collections::HashMap;
 use std::time::Instant;
 
 use crate::lookup_tables::*;
@@ -19,6 +21,7 @@ use pyrrhic_rs::TableBases;
 use pyrrhic_rs::WdlProbeResult;
 use Piece::*;
 
+use self::see::SEE_PIECE_VALUES;
 use self::tb_adapter::MovegenAdapter;
 
 macro_rules! select_colored_pieces {
@@ -51,6 +54,33 @@ macro_rules! select_colored_pieces {
     };
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IllegalMove(pub Move);
+
+impl std::fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Illegal move: {}", self.0)
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+// everything `Board::unmake_move` can't recompute from the move itself
+#[derive(Copy, Clone, Debug)]
+pub struct UnMove {
+    mv: Move,
+    capture: Option<Piece>,
+    castling: bool,
+    castling_rights: [[BitBoard; 2]; 2],
+    check_mask: BitBoard,
+    diagonal_pin_mask: BitBoard,
+    orthogonal_pin_mask: BitBoard,
+    ep_mask: BitBoard,
+    halfmove_clock: u16,
+    hash: u64,
+    pawn_hash: u64,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Board {
     white_pawns: BitBoard,
@@ -68,22 +98,89 @@ pub struct Board {
     white_pieces: BitBoard,
     black_pieces: BitBoard,
     occupied: BitBoard,
+    mailbox: [Option<Piece>; 64],
     castling_rights: [[BitBoard; 2]; 2],
     check_mask: BitBoard,
     pub diagonal_pin_mask: BitBoard,
     pub orthogonal_pin_mask: BitBoard,
     ep_mask: BitBoard,
     black_to_move: bool,
-    halfmove_clock: u8,
+    halfmove_clock: u16,
     hash: u64,
     pawn_hash: u64,
 }
 
+// positions are stored as FEN strings, keeping the format readable and independent of the
+// internal layout
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.fen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Board::from_fen(&fen).ok_or_else(|| serde::de::Error::custom(format!("Invalid FEN: {fen}")))
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    // lets tests simulate a bad incremental hash update on one particular move
+    static CORRUPT_HASH_AFTER: std::cell::Cell<Option<Move>> = const { std::cell::Cell::new(None) };
+}
+
 impl Board {
     pub fn new() -> Self {
         Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
     }
 
+    // Chess960 start position n (0..960) from Scharnagl's numbering, 518 being the standard one
+    pub fn frc(mut n: usize) -> Self {
+        let mut rank = [' '; 8];
+        rank[(n % 4) * 2 + 1] = 'b';
+        n /= 4;
+        rank[(n % 4) * 2] = 'b';
+        n /= 4;
+        let mut empty: Vec<usize> = (0..8).filter(|&f| rank[f] == ' ').collect();
+        rank[empty.remove(n % 6)] = 'q';
+        n /= 6;
+        let knights = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ][n % 10];
+        rank[empty[knights.0]] = 'n';
+        rank[empty[knights.1]] = 'n';
+        let empty: Vec<usize> = (0..8).filter(|&f| rank[f] == ' ').collect();
+        for (&file, piece) in empty.iter().zip(['r', 'k', 'r']) {
+            rank[file] = piece;
+        }
+
+        let black: String = rank.iter().collect();
+        let rooks: String = (0..8)
+            .filter(|&f| rank[f] == 'r')
+            .rev()
+            .map(|f| (b'a' + f as u8) as char)
+            .collect();
+        let fen = format!(
+            "{black}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{rooks} - 0 1",
+            black.to_ascii_uppercase(),
+            rooks.to_ascii_uppercase(),
+        );
+        Self::from_fen(&fen).unwrap()
+    }
+
     pub fn perft(&self, depth: usize) {
         let mut nodes = 0;
         if depth == 0 {
@@ -131,6 +228,49 @@ impl Board {
         nodes
     }
 
+    pub fn perft_verify(&self, depth: usize) {
+        let start = Instant::now();
+        let result = self.verify_hashes(depth);
+        let end = Instant::now();
+
+        match result {
+            Ok(nodes) => println!(
+                "Nodes: {nodes}\t\tNPS: {}\t\tHashes verified",
+                (nodes as f64 / (end - start).as_secs_f64()) as usize
+            ),
+            Err(fen) => println!("Hash mismatch in position: {fen}"),
+        }
+    }
+
+    // walk the perft tree recomputing the hashes at every node, returning the FEN of the first
+    // position where the incremental hashes diverge. Unlike the debug assertions in `make_move`
+    // this also runs in release builds
+    pub fn verify_hashes(&self, depth: usize) -> Result<usize, String> {
+        if self.hash != self.calculate_hash() || self.pawn_hash != self.calculate_pawn_hash() {
+            return Err(self.fen());
+        }
+        if depth == 0 {
+            return Ok(1);
+        }
+
+        let mut nodes = 0;
+        let mut result = Ok(());
+        self.generate_legal_moves(|mvs| {
+            for mv in mvs {
+                if result.is_err() {
+                    return;
+                }
+                let mut new = *self;
+                new.make_move(mv);
+                match new.verify_hashes(depth - 1) {
+                    Ok(subnodes) => nodes += subnodes,
+                    Err(fen) => result = Err(fen),
+                }
+            }
+        });
+        result.map(|_| nodes)
+    }
+
     #[inline(always)]
     pub fn current_player(&self) -> Color {
         if self.black_to_move {
@@ -145,8 +285,22 @@ impl Board {
         self.check_mask != FULL_BOARD
     }
 
+    // the squares of the pieces giving check. the check mask only has the squares a check can
+    // be blocked on, and nothing at all in double check
+    pub fn checkers(&self) -> BitBoard {
+        if !self.in_check() {
+            return BitBoard::empty();
+        }
+        let (king, enemy_pieces) = if self.black_to_move {
+            (self.black_king, self.white_pieces)
+        } else {
+            (self.white_king, self.black_pieces)
+        };
+        self.all_attacks_on(king.first_square(), self.occupied) & enemy_pieces
+    }
+
     #[inline(always)]
-    pub fn halfmove_clock(&self) -> u8 {
+    pub fn halfmove_clock(&self) -> u16 {
         self.halfmove_clock
     }
 
@@ -155,6 +309,22 @@ impl Board {
         self.hash
     }
 
+    #[inline(always)]
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    // the square a pawn would move to when capturing en passant, e.g. e3 after 1. e4. This is
+    // only set if an enemy pawn can actually make the capture
+    #[inline(always)]
+    pub fn en_passant_square(&self) -> Option<Square> {
+        if self.ep_mask.is_empty() {
+            None
+        } else {
+            Some(self.ep_mask.first_square())
+        }
+    }
+
     #[inline(always)]
     pub fn castling_rights(&self) -> &[[BitBoard; 2]; 2] {
         &self.castling_rights
@@ -171,6 +341,18 @@ impl Board {
         }
     }
 
+    // captures and promotions, what the quiescence search looks at
+    #[inline(always)]
+    pub fn is_tactical(&self, mv: Move) -> bool {
+        mv.is_promotion() || self.is_capture(mv)
+    }
+
+    // everything else, castling included
+    #[inline(always)]
+    pub fn is_quiet(&self, mv: Move) -> bool {
+        !self.is_tactical(mv)
+    }
+
     #[inline(always)]
     pub fn has_non_pawn_material(&self, color: Color) -> bool {
         let material = if color == Color::White {
@@ -203,11 +385,25 @@ impl Board {
 
     #[inline(always)]
     pub fn pawn_count(&self, color: Color) -> usize {
-        if color == Color::White {
-            self.white_pawns.count_ones() as usize
+        self.piece_count_of(color, Pawn)
+    }
+
+    #[inline(always)]
+    pub fn piece_count_of(&self, color: Color, piece: Piece) -> usize {
+        let pieces = if color == Color::White {
+            self.pieces::<White>()
         } else {
-            self.black_pawns.count_ones() as usize
-        }
+            self.pieces::<Black>()
+        };
+        pieces[piece].count_ones() as usize
+    }
+
+    // material in SEE_PIECE_VALUES, not counting the king
+    pub fn material_count(&self, color: Color) -> i32 {
+        [Pawn, Knight, Bishop, Rook, Queen]
+            .into_iter()
+            .map(|piece| self.piece_count_of(color, piece) as i32 * SEE_PIECE_VALUES[piece] as i32)
+            .sum()
     }
 
     #[inline(always)]
@@ -226,6 +422,17 @@ impl Board {
             | (lookup_king(target) & kings)
     }
 
+    // pieces of one color attacking a square, with sliders stopped by the occupancy given.
+    // pass the board's occupancy, or clear pieces from it to see x-ray attacks behind them
+    #[inline(always)]
+    pub fn attackers_to(&self, square: Square, by_color: Color, occupied: BitBoard) -> BitBoard {
+        let pieces = match by_color {
+            Color::White => self.white_pieces,
+            Color::Black => self.black_pieces,
+        };
+        self.all_attacks_on(square, occupied) & pieces
+    }
+
     #[inline(always)]
     pub fn forward<T: TypeColor>(&self, mask: BitBoard) -> BitBoard {
         if T::WHITE {
@@ -481,6 +688,7 @@ impl Board {
         }
     }
 
+    // all pieces on the board, kings and pawns included
     #[inline(always)]
     pub fn piece_count(&self) -> u32 {
         (self.white_pieces | self.black_pieces).count_ones() as u32
@@ -493,6 +701,10 @@ impl Board {
         *board ^= mask;
         *pieces ^= mask;
         self.occupied ^= mask;
+        self.mailbox[square] = match self.mailbox[square] {
+            Some(_) => None,
+            None => Some(piece),
+        };
         self.hash ^= zobrist_piece::<T>(piece, square);
     }
 
@@ -510,6 +722,9 @@ impl Board {
         self.hash ^= zobrist_piece::<T>(piece, start);
         self.occupied |= target_mask;
         self.hash ^= zobrist_piece::<T>(piece, target);
+
+        self.mailbox[start] = None;
+        self.mailbox[target] = Some(piece);
     }
 
     #[inline(always)]
@@ -523,43 +738,20 @@ impl Board {
 
     #[inline(always)]
     pub fn piece_on(&self, square: Square) -> Option<Piece> {
-        let mask = square.bitboard();
-        if (self.occupied & mask).is_empty() {
-            None
-        } else if (self.white_pieces & mask).is_not_empty() {
-            if ((self.white_pawns | self.white_knights | self.white_bishops) & mask).is_not_empty()
-            {
-                if (self.white_pawns & mask).is_not_empty() {
-                    Some(Pawn)
-                } else if (self.white_knights & mask).is_not_empty() {
-                    Some(Knight)
-                } else {
-                    Some(Bishop)
-                }
-            } else if (self.white_rooks & mask).is_not_empty() {
-                Some(Rook)
-            } else if (self.white_queens & mask).is_not_empty() {
-                Some(Queen)
-            } else {
-                Some(King)
-            }
-        } else if ((self.black_pawns | self.black_knights | self.black_bishops) & mask)
-            .is_not_empty()
-        {
-            if (self.black_pawns & mask).is_not_empty() {
-                Some(Pawn)
-            } else if (self.black_knights & mask).is_not_empty() {
-                Some(Knight)
-            } else {
-                Some(Bishop)
-            }
-        } else if (self.black_rooks & mask).is_not_empty() {
-            Some(Rook)
-        } else if (self.black_queens & mask).is_not_empty() {
-            Some(Queen)
-        } else {
-            Some(King)
-        }
+        self.mailbox[square]
+    }
+
+    // check the mailbox agrees with the piece bitboards on every square
+    fn mailbox_in_sync(&self) -> bool {
+        [Pawn, Knight, Bishop, Rook, Queen, King]
+            .into_iter()
+            .all(|piece| {
+                (self.piece_mask(false, piece) | self.piece_mask(true, piece))
+                    .squares()
+                    .all(|square| self.mailbox[square] == Some(piece))
+            })
+            && self.mailbox.iter().filter(|p| p.is_some()).count()
+                == self.occupied.count_ones() as usize
     }
 
     pub fn probe_wdl(&self, tb: &TableBases<MovegenAdapter>) -> Result<WdlProbeResult, TBError> {
@@ -731,12 +923,53 @@ impl Board {
             }
         }
     }
+
+    // dead position: only kings and bishops on a single colour of squares remain, so no
+    // sequence of legal moves can ever lead to checkmate
+    pub fn is_dead_position(&self) -> bool {
+        let bishops = self.white_bishops | self.black_bishops;
+        let kings = self.white_king | self.black_king;
+        (bishops | kings) == self.occupied
+            && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & DARK_SQUARES).is_empty())
+    }
+
+    // strict FIDE insufficient material: KvK, KNvK, and kings with any bishops all on one
+    // colour of squares (KBvK, KBvKB). unlike `material_draw` this leaves out positions that are
+    // only drawn with correct play, like KRvKR, which can still be lost on time
+    pub fn is_insufficient_material(&self) -> bool {
+        let kings = self.white_king | self.black_king;
+        let knights = self.white_knights | self.black_knights;
+        self.is_dead_position() || (knights.count_ones() == 1 && (kings | knights) == self.occupied)
+    }
+
+    // the result if the game is over on the board: checkmate, stalemate, the fifty move rule or
+    // insufficient material. repetitions need the game history, so they aren't detected here
+    pub fn game_result(&self) -> Option<GameOutcome> {
+        if !self.has_legal_move() {
+            return Some(if self.in_check() {
+                GameOutcome::loss_for(self.current_player())
+            } else {
+                GameOutcome::Draw
+            });
+        }
+        if self.halfmove_clock() >= 100 || self.is_insufficient_material() {
+            return Some(GameOutcome::Draw);
+        }
+        None
+    }
+
     pub fn material_draw(&self) -> bool {
         // do not report any positions with pawns as material draws
         if (self.white_pawns | self.black_pawns).is_not_empty() {
             return false;
         }
 
+        // kings and bishops that all share a square colour can never mate, however many
+        // bishops there are. the KBvK and KBvKB cases below also cover opposite bishops
+        if self.is_dead_position() {
+            return true;
+        }
+
         // KNvK
         if (self.white_knights.count_ones() == 1 && self.black_pieces.count_ones() == 1)
             || (self.black_knights.count_ones() == 1 && self.white_pieces.count_ones() == 1)
@@ -783,6 +1016,35 @@ impl Board {
         false
     }
 
+    // the USCF-style material rule for timeouts: a side left with a bare king or a lone minor
+    // piece is treated as unable to win, so flagging against it is a draw. this only counts
+    // material, and a lone knight can still mate when the opponent's own pieces box its king in
+    pub fn cannot_win(&self, color: Color) -> bool {
+        let (pieces, pawns, knights, bishops) = if color == Color::White {
+            (
+                self.white_pieces,
+                self.white_pawns,
+                self.white_knights,
+                self.white_bishops,
+            )
+        } else {
+            (
+                self.black_pieces,
+                self.black_pawns,
+                self.black_knights,
+                self.black_bishops,
+            )
+        };
+
+        match pieces.count_ones() {
+            // KvX
+            1 => true,
+            // KNvX or KBvX
+            2 => pawns.is_empty() && (knights | bishops).is_not_empty(),
+            _ => false,
+        }
+    }
+
     pub fn make_move(&mut self, mv: Move) {
         if self.black_to_move {
             self.make_move_for::<Black>(mv);
@@ -791,6 +1053,244 @@ impl Board {
         }
     }
 
+    // make a move that can be taken back with `unmake_move`, for callers that can't afford a
+    // board per ply. in perft this is no faster than copying the 272 byte board, movegen
+    // dominates either way, so the search sticks with copy-make
+    pub fn make_move_undoable(&mut self, mv: Move) -> UnMove {
+        // castling is encoded as king captures friendly rook
+        let own = if self.black_to_move {
+            self.black_pieces
+        } else {
+            self.white_pieces
+        };
+        let castling = (own & mv.to().bitboard()).is_not_empty();
+        let unmove = UnMove {
+            mv,
+            capture: self.piece_on(mv.to()).filter(|_| !castling),
+            castling,
+            castling_rights: self.castling_rights,
+            check_mask: self.check_mask,
+            diagonal_pin_mask: self.diagonal_pin_mask,
+            orthogonal_pin_mask: self.orthogonal_pin_mask,
+            ep_mask: self.ep_mask,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+        };
+        self.make_move(mv);
+        unmove
+    }
+
+    // take back the last move made with `make_move_undoable`
+    pub fn unmake_move(&mut self, unmove: UnMove) {
+        // the side that made the move is the one not to move now
+        if self.black_to_move {
+            self.unmake_move_for::<White>(unmove);
+        } else {
+            self.unmake_move_for::<Black>(unmove);
+        }
+    }
+
+    fn unmake_move_for<T: TypeColor>(&mut self, unmove: UnMove) {
+        let mv = unmove.mv;
+
+        // undo everything in the opposite order to `make_move_for`, so the mailbox toggles
+        // line up when FRC castling squares overlap
+        if unmove.castling {
+            let (king_target, rook_target) = if T::WHITE {
+                if mv.from().file() < mv.to().file() {
+                    (Square::G1, Square::F1)
+                } else {
+                    (Square::C1, Square::D1)
+                }
+            } else if mv.from().file() < mv.to().file() {
+                (Square::G8, Square::F8)
+            } else {
+                (Square::C8, Square::D8)
+            };
+            self.xor_piece::<T>(Rook, rook_target);
+            self.xor_piece::<T>(King, king_target);
+            self.xor_piece::<T>(Rook, mv.to());
+            self.xor_piece::<T>(King, mv.from());
+        } else {
+            if mv.promotion() != Pawn {
+                self.xor_piece::<T>(mv.promotion(), mv.to());
+                self.xor_piece::<T>(Pawn, mv.to());
+            } else if mv.piece() == Pawn && mv.to().bitboard() == unmove.ep_mask {
+                let target_square = self.forward::<T::Other>(unmove.ep_mask).first_square();
+                self.xor_piece::<T::Other>(Pawn, target_square);
+            }
+            self.move_piece::<T>(mv.piece(), mv.to(), mv.from());
+            if let Some(capture) = unmove.capture {
+                self.xor_piece::<T::Other>(capture, mv.to());
+            }
+        }
+
+        self.castling_rights = unmove.castling_rights;
+        self.check_mask = unmove.check_mask;
+        self.diagonal_pin_mask = unmove.diagonal_pin_mask;
+        self.orthogonal_pin_mask = unmove.orthogonal_pin_mask;
+        self.ep_mask = unmove.ep_mask;
+        self.halfmove_clock = unmove.halfmove_clock;
+        self.hash = unmove.hash;
+        self.pawn_hash = unmove.pawn_hash;
+        self.black_to_move = !self.black_to_move;
+
+        debug_assert!(self.hash == self.calculate_hash());
+        debug_assert!(self.pawn_hash == self.calculate_pawn_hash());
+        debug_assert!(self.mailbox_in_sync());
+    }
+
+    // standard algebraic notation for a legal move in this position, e.g. Nbd7, exd6, e8=Q+
+    // or O-O-O#. castling comes from the king-takes-rook encoding, so works for FRC too
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let own = if self.black_to_move {
+            self.black_pieces
+        } else {
+            self.white_pieces
+        };
+        let letter = |piece: Piece| match piece {
+            Knight => "N",
+            Bishop => "B",
+            Rook => "R",
+            Queen => "Q",
+            King => "K",
+            Pawn => "",
+        };
+
+        let mut san = String::new();
+        if mv.piece() == King && (mv.to().bitboard() & own).is_not_empty() {
+            san.push_str(if mv.to().file() < mv.from().file() {
+                "O-O-O"
+            } else {
+                "O-O"
+            });
+        } else {
+            let capture = self.is_capture(mv);
+            if mv.piece() == Pawn {
+                if capture {
+                    san.push_str(mv.from().file_letter());
+                }
+            } else {
+                san.push_str(letter(mv.piece()));
+                // other pieces of the same type that could also reach the target square
+                let moves = self.legal_moves();
+                let others: Vec<Square> = moves
+                    .inner()
+                    .iter()
+                    .map(|smv| smv.mv)
+                    .filter(|m| {
+                        m.piece() == mv.piece() && m.to() == mv.to() && m.from() != mv.from()
+                    })
+                    .map(|m| m.from())
+                    .collect();
+                if others.iter().all(|sq| sq.file() != mv.from().file()) {
+                    if !others.is_empty() {
+                        san.push_str(mv.from().file_letter());
+                    }
+                } else if others.iter().all(|sq| sq.rank() != mv.from().rank()) {
+                    san.push_str(&(mv.from().rank() + 1).to_string());
+                } else {
+                    san.push_str(&mv.from().coord());
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to().coord());
+            if mv.promotion() != Pawn {
+                san.push('=');
+                san.push_str(letter(mv.promotion()));
+            }
+        }
+
+        if self.gives_check(mv) {
+            let mut after = *self;
+            after.make_move(mv);
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    // whether a pseudolegal move checks the enemy king, without making it. covers direct checks
+    // from the moved (or promoted) piece, the rook after castling, and discovered checks from
+    // sliders behind the vacated squares, en passant included
+    pub fn gives_check(&self, mv: Move) -> bool {
+        if self.black_to_move {
+            self.gives_check_for::<Black>(mv)
+        } else {
+            self.gives_check_for::<White>(mv)
+        }
+    }
+
+    fn gives_check_for<T: TypeColor>(&self, mv: Move) -> bool {
+        let ours = self.pieces::<T>();
+        let king = self.pieces::<T::Other>()[King].first_square();
+        let (from, to) = (mv.from().bitboard(), mv.to().bitboard());
+
+        // castling is encoded as king captures friendly rook
+        if mv.piece() == King && (self.color::<T>() & to).is_not_empty() {
+            let (king_target, rook_target) = match (T::WHITE, mv.from().file() < mv.to().file()) {
+                (true, true) => (Square::G1, Square::F1),
+                (true, false) => (Square::C1, Square::D1),
+                (false, true) => (Square::G8, Square::F8),
+                (false, false) => (Square::C8, Square::D8),
+            };
+            let occupied =
+                (self.occupied ^ from ^ to) | king_target.bitboard() | rook_target.bitboard();
+            return (lookup_rook(rook_target, occupied) & king.bitboard()).is_not_empty();
+        }
+
+        let mut occupied = (self.occupied & from.inverse()) | to;
+        if mv.piece() == Pawn && to == self.ep_mask {
+            let captured = if T::WHITE {
+                mv.to().offset(0, -1)
+            } else {
+                mv.to().offset(0, 1)
+            };
+            occupied ^= captured.bitboard();
+        }
+
+        let piece = if mv.promotion() == Pawn {
+            mv.piece()
+        } else {
+            mv.promotion()
+        };
+        let direct = match piece {
+            Pawn => Self::pawn_attack::<T>(mv.to()),
+            Knight => lookup_knight(mv.to()),
+            Bishop => lookup_bishop(mv.to(), occupied),
+            Rook => lookup_rook(mv.to(), occupied),
+            Queen => lookup_queen(mv.to(), occupied),
+            King => BitBoard::empty(),
+        };
+        if (direct & king.bitboard()).is_not_empty() {
+            return true;
+        }
+
+        // the moved piece is already covered from its new square
+        let others = (from | to).inverse();
+        let diagonal = (ours[Bishop] | ours[Queen]) & others;
+        let orthogonal = (ours[Rook] | ours[Queen]) & others;
+        (lookup_bishop(king, occupied) & diagonal).is_not_empty()
+            || (lookup_rook(king, occupied) & orthogonal).is_not_empty()
+    }
+
+    // make a move only if it is legal in this position, leaving the board untouched otherwise.
+    // `make_move` trusts its input and will corrupt the board if given an illegal move
+    pub fn make_move_checked(&mut self, mv: Move) -> Result<(), IllegalMove> {
+        if self.legal_moves().contains(mv) {
+            self.make_move(mv);
+            Ok(())
+        } else {
+            Err(IllegalMove(mv))
+        }
+    }
+
     fn make_move_for<T: TypeColor>(&mut self, mv: Move) {
         let piece = mv.piece();
         let start_mask = mv.from().bitboard();
@@ -810,7 +1310,7 @@ impl Board {
         self.orthogonal_pin_mask = BitBoard::empty();
         self.check_mask = FULL_BOARD;
 
-        self.halfmove_clock += 1;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
 
         if castling {
             // select the target squares for king and rook
@@ -990,6 +1490,12 @@ impl Board {
 
         debug_assert!(self.hash == self.calculate_hash());
         debug_assert!(self.pawn_hash == self.calculate_pawn_hash());
+        debug_assert!(self.mailbox_in_sync());
+
+        #[cfg(test)]
+        if CORRUPT_HASH_AFTER.get() == Some(mv) {
+            self.hash ^= 1;
+        }
     }
 
     pub fn make_null_move(&mut self) {
@@ -999,7 +1505,7 @@ impl Board {
 
         self.black_to_move = !self.black_to_move;
         self.hash ^= zobrist_player();
-        self.halfmove_clock += 1;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
 
         // null move can never be made when in check
         self.check_mask = FULL_BOARD;
@@ -1012,6 +1518,30 @@ impl Board {
         }
     }
 
+    // the same position with the other side to move, for analysis rather than search: unlike
+    // the null move the halfmove clock is left alone and the check mask is recomputed. The en
+    // passant square is dropped, it only belonged to the side that was to move. If that side
+    // is in check the result is illegal, see `illegal_position`
+    pub fn with_side_to_move_swapped(&self) -> Board {
+        let mut board = *self;
+        board.hash ^= zobrist_ep(board.ep_mask);
+        board.ep_mask = BitBoard::empty();
+        board.hash ^= zobrist_ep(board.ep_mask);
+
+        board.black_to_move = !board.black_to_move;
+        board.hash ^= zobrist_player();
+
+        if board.black_to_move {
+            board.calculate_check_mask::<Black>();
+            board.calculate_pin_masks::<Black>();
+        } else {
+            board.calculate_check_mask::<White>();
+            board.calculate_pin_masks::<White>();
+        }
+        debug_assert!(board.hash == board.calculate_hash());
+        board
+    }
+
     #[inline(always)]
     pub fn calculate_check_mask<T: TypeColor>(&mut self) {
         let (king, enemy_pawns, enemy_knights, enemy_bishops, enemy_rooks) = if T::WHITE {
@@ -1164,7 +1694,7 @@ impl Board {
         let mut fen = fen.as_ref().split_whitespace();
         let pieces = fen.next()?;
         let stm = fen.next()?;
-        let castling_rights = fen.next()?;
+        let castling_field = fen.next()?;
         let ep_square = fen.next()?;
         let halfmove_clock = fen.next()?;
 
@@ -1228,11 +1758,125 @@ impl Board {
             BitBoard::empty()
         };
 
+        let mut castling_rights = [[BitBoard::empty(); 2]; 2];
+        if castling_field != "-" {
+            // either classic KQkq, where the right belongs to the outermost rook on that side
+            // (X-FEN), or Shredder-FEN naming the rook's file. Mixing the two is ambiguous
+            let classic = castling_field.chars().all(|c| "KQkq".contains(c));
+            let shredder = castling_field
+                .chars()
+                .all(|c| matches!(c.to_ascii_lowercase(), 'a'..='h'));
+            if !classic && !shredder {
+                return None;
+            }
+
+            // GUIs and EPD suites often send rights that can't exist, like KQkq in an
+            // endgame. those tokens are dropped rather than rejecting the whole position
+            for c in castling_field.chars() {
+                let black = c.is_ascii_lowercase();
+                let (king, rooks) = if black {
+                    (black_king, black_rooks)
+                } else {
+                    (white_king, white_rooks)
+                };
+                let back_rank = if black { 7 } else { 0 };
+                let king_square = king.first_square();
+                if king_square.rank() != back_rank {
+                    continue;
+                }
+                let king_file = king_square.file();
+                let has_rook = |file: usize| {
+                    (rooks & Square::from((back_rank * 8 + file) as u8).bitboard()).is_not_empty()
+                };
+
+                let file = match c.to_ascii_lowercase() {
+                    'k' => (king_file + 1..8).rev().find(|&f| has_rook(f)),
+                    'q' => (0..king_file).find(|&f| has_rook(f)),
+                    f => Some(f as usize - 'a' as usize),
+                };
+                let Some(file) = file.filter(|&f| f != king_file && has_rook(f)) else {
+                    continue;
+                };
+                let queenside = file < king_file;
+                let mask = BitBoard(1 << (file + 56 * black as usize));
+                castling_rights[black as usize][queenside as usize] = mask;
+            }
+        }
+
+        Some(Self::from_pieces(
+            [
+                [
+                    white_pawns,
+                    white_knights,
+                    white_bishops,
+                    white_rooks,
+                    white_queens,
+                    white_king,
+                ],
+                [
+                    black_pawns,
+                    black_knights,
+                    black_bishops,
+                    black_rooks,
+                    black_queens,
+                    black_king,
+                ],
+            ],
+            stm == "b",
+            castling_rights,
+            ep_mask,
+            halfmove_clock.parse::<u16>().ok()?,
+        ))
+    }
+
+    // a board from its piece bitboards, indexed by colour then piece, with the mailbox, check
+    // and pin masks and hashes filled in from them
+    fn from_pieces(
+        pieces: [[BitBoard; 6]; 2],
+        black_to_move: bool,
+        castling_rights: [[BitBoard; 2]; 2],
+        mut ep_mask: BitBoard,
+        halfmove_clock: u16,
+    ) -> Self {
+        let [white, black] = pieces;
+        let [white_pawns, white_knights, white_bishops, white_rooks, white_queens, white_king] =
+            white;
+        let [black_pawns, black_knights, black_bishops, black_rooks, black_queens, black_king] =
+            black;
+
+        // like make_move, only keep the square if a pawn can capture onto it, so the hash
+        // doesn't depend on whether the position came from a FEN or from playing the moves
+        if ep_mask.is_not_empty() {
+            let capturers = if black_to_move {
+                Self::pawn_attack::<White>(ep_mask.first_square()) & black_pawns
+            } else {
+                Self::pawn_attack::<Black>(ep_mask.first_square()) & white_pawns
+            };
+            if capturers.is_empty() {
+                ep_mask = BitBoard::empty();
+            }
+        }
+
         let white_pieces =
             white_pawns | white_knights | white_bishops | white_rooks | white_queens | white_king;
         let black_pieces =
             black_pawns | black_knights | black_bishops | black_rooks | black_queens | black_king;
         let occupied = white_pieces | black_pieces;
+
+        let mut mailbox = [None; 64];
+        for (piece, pieces) in [
+            (Pawn, white_pawns | black_pawns),
+            (Knight, white_knights | black_knights),
+            (Bishop, white_bishops | black_bishops),
+            (Rook, white_rooks | black_rooks),
+            (Queen, white_queens | black_queens),
+            (King, white_king | black_king),
+        ] {
+            for square in pieces {
+                mailbox[square] = Some(piece);
+            }
+        }
+
         let mut board = Self {
             white_pawns,
             black_pawns,
@@ -1249,13 +1893,14 @@ impl Board {
             white_pieces,
             black_pieces,
             occupied,
-            castling_rights: [[BitBoard::empty(); 2]; 2],
+            mailbox,
+            castling_rights,
             check_mask: FULL_BOARD,
             diagonal_pin_mask: BitBoard::empty(),
             orthogonal_pin_mask: BitBoard::empty(),
-            black_to_move: stm == "b",
+            black_to_move,
             ep_mask,
-            halfmove_clock: halfmove_clock.parse::<u8>().ok()?,
+            halfmove_clock,
             hash: 0,
             pawn_hash: 0,
         };
@@ -1268,35 +1913,32 @@ impl Board {
             board.calculate_pin_masks::<White>();
         }
 
-        if castling_rights != "-" {
-            for c in castling_rights.chars() {
-                let black = c.is_ascii_lowercase();
-                let king_file = if black {
-                    board.black_king.first_square().file()
-                } else {
-                    board.white_king.first_square().file()
-                } as u64;
-                let file = match c.to_ascii_lowercase() {
-                    'k' | 'h' => 7,
-                    'q' | 'a' => 0,
-                    'b' => 1,
-                    'c' => 2,
-                    'd' => 3,
-                    'e' => 4,
-                    'f' => 5,
-                    'g' => 6,
-                    _ => return None,
-                } as u64;
-                let queenside = file < king_file;
-                let mask = BitBoard(1 << (file + (56 * black as u64)));
-                board.castling_rights[black as usize][queenside as usize] = mask;
-            }
-        }
-
         board.hash = board.calculate_hash();
         board.pawn_hash = board.calculate_pawn_hash();
 
-        Some(board)
+        board
+    }
+
+    // the same position with the colours swapped and the board flipped vertically, to check the
+    // evaluation treats both sides alike
+    pub fn mirror(&self) -> Board {
+        // flipping the ranks is reversing the bytes of each bitboard
+        let flip = |bb: BitBoard| BitBoard(bb.0.swap_bytes());
+        // white gets black's pieces and the other way round
+        let pieces =
+            [true, false].map(|black| PIECES.map(|piece| flip(self.piece_mask(black, piece))));
+        let castling_rights = [
+            self.castling_rights[1].map(flip),
+            self.castling_rights[0].map(flip),
+        ];
+
+        Self::from_pieces(
+            pieces,
+            !self.black_to_move,
+            castling_rights,
+            flip(self.ep_mask),
+            self.halfmove_clock,
+        )
     }
 
     pub fn fen(&self) -> String {
@@ -1364,9 +2006,18 @@ impl Board {
                 }
             }
 
-            if rights_string
-                .chars()
-                .all(|c| matches!(c, 'a' | 'A' | 'h' | 'H'))
+            // standard positions use KQkq, anything else keeps the Shredder-FEN files so it
+            // reads back exactly as it was given
+            let kings_on_e_file = [self.white_king, self.black_king]
+                .iter()
+                .zip(self.castling_rights)
+                .all(|(king, rights)| {
+                    rights.iter().all(|r| r.is_empty()) || king.first_square().file() == 4
+                });
+            if kings_on_e_file
+                && rights_string
+                    .chars()
+                    .all(|c| matches!(c, 'a' | 'A' | 'h' | 'H'))
             {
                 rights_string = rights_string
                     .chars()
@@ -1398,6 +2049,61 @@ impl Board {
         fen
     }
 
+    // EPD: the first four FEN fields followed by `opcode operand;` operations, like
+    // `bm Nf3; id "pos1";`. Operands are written as given, so string operands need their quotes
+    pub fn epd(&self, ops: &[(&str, &str)]) -> String {
+        let fen = self.fen();
+        let mut epd = fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+        for (opcode, operand) in ops {
+            epd.push_str(&format!(" {opcode} {operand};"));
+        }
+        epd
+    }
+
+    // the position and operations of an EPD line, with quoted operands unquoted. EPD has no
+    // move counters, but an `hmvc` operation sets the halfmove clock
+    pub fn from_epd<T: AsRef<str>>(epd: T) -> Option<(Self, HashMap<String, String>)> {
+        let mut rest = epd.as_ref();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        // operations end at semicolons, except inside quoted strings
+        let mut ops = HashMap::new();
+        let mut quoted = false;
+        let mut start = 0;
+        for (i, c) in rest.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    let op = rest[start..i].trim();
+                    let (opcode, operand) = op.split_once(' ').unwrap_or((op, ""));
+                    let operand = operand.trim();
+                    let operand = operand
+                        .strip_prefix('"')
+                        .and_then(|o| o.strip_suffix('"'))
+                        .unwrap_or(operand);
+                    if !opcode.is_empty() {
+                        ops.insert(opcode.to_string(), operand.to_string());
+                    }
+                    start = i + 1;
+                }
+                _ => (),
+            }
+        }
+        if quoted || !rest[start..].trim().is_empty() {
+            return None;
+        }
+
+        let halfmove_clock = ops.get("hmvc").map_or("0", String::as_str);
+        let board = Self::from_fen(format!("{} {halfmove_clock} 1", fields.join(" ")))?;
+        Some((board, ops))
+    }
+
     pub fn dump_state(&self) {
         println!("White Pawns:\n{}\n", self.white_pawns);
         println!("Black Pawns:\n{}\n", self.black_pawns);
@@ -1437,3 +2143,811 @@ impl Default for Board {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::{BitBoard, Square};
+
+    use crate::{
+        hash_tables::PawnHashTable,
+        moves::{Move, MoveList},
+        types::{Color::*, GameOutcome, Piece::*},
+    };
+
+    use super::{Board, IllegalMove, CORRUPT_HASH_AFTER};
+
+    #[test]
+    fn test_verify_hashes() {
+        let board = Board::new();
+        assert_eq!(board.verify_hashes(3), Ok(8902));
+
+        let mut corrupted = board;
+        corrupted.hash ^= 1;
+        assert_eq!(corrupted.verify_hashes(3), Err(board.fen()));
+
+        let mut corrupted = board;
+        corrupted.pawn_hash ^= 1;
+        assert_eq!(corrupted.verify_hashes(3), Err(board.fen()));
+
+        // a bad update deeper in the tree is reported with the position it led to. the en
+        // passant capture is only possible in the one line
+        let board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        let ep_capture = Move::new(Pawn, Square::E5, Square::D6, Pawn);
+        let mut expected = board;
+        expected.make_move(Move::new(Pawn, Square::D7, Square::D5, Pawn));
+        expected.make_move(ep_capture);
+
+        CORRUPT_HASH_AFTER.set(Some(ep_capture));
+        let result = board.verify_hashes(3);
+        CORRUPT_HASH_AFTER.set(None);
+        assert_eq!(result, Err(expected.fen()));
+    }
+
+    #[test]
+    fn test_make_move_checked() {
+        let board = Board::new();
+
+        let mut new = board;
+        assert!(new
+            .make_move_checked(Move::new(Pawn, Square::E2, Square::E4, Pawn))
+            .is_ok());
+        assert_eq!(new.piece_on(Square::E4), Some(Pawn));
+
+        for mv in [
+            // wrong piece type for the square
+            Move::new(Knight, Square::E2, Square::E4, Pawn),
+            // not a legal destination
+            Move::new(Pawn, Square::E2, Square::E5, Pawn),
+            // no piece on the start square
+            Move::new(Queen, Square::D4, Square::D5, Pawn),
+            Move::null(),
+        ] {
+            let mut new = board;
+            assert_eq!(new.make_move_checked(mv), Err(IllegalMove(mv)));
+            assert_eq!(new.fen(), board.fen());
+            assert_eq!(new.hash(), board.hash());
+        }
+    }
+
+    #[test]
+    fn test_promotion_check_mask() {
+        // promotions and capture-promotions giving check with each piece type, plus a
+        // discovered check from the promoting pawn uncovering a rook
+        for fen in [
+            "3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1",
+            "2k5/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/3p4/2N4K b - - 0 1",
+            "8/8/8/8/8/k7/4p3/2K2N2 b - - 0 1",
+            "8/8/8/8/8/k7/4p1K1/5N2 b - - 0 1",
+            "8/R5Pk/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut checks = 0;
+            for mv in board.legal_move_list() {
+                if mv.promotion() == Pawn {
+                    continue;
+                }
+                let mut new = board;
+                new.make_move(mv);
+                checks += new.in_check() as usize;
+
+                let fresh = Board::from_fen(new.fen()).unwrap();
+                assert_eq!(
+                    new.check_mask, fresh.check_mask,
+                    "Check mask after {mv} in {fen}"
+                );
+                assert_eq!(new.diagonal_pin_mask, fresh.diagonal_pin_mask);
+                assert_eq!(new.orthogonal_pin_mask, fresh.orthogonal_pin_mask);
+            }
+            assert!(checks > 0, "No checking promotions in {fen}");
+        }
+    }
+
+    #[test]
+    fn test_dead_position() {
+        for (fen, dead) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/2b1b3/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/2b5/8/3BK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/2b5/8/2BNK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/2b5/4P3/2B1K3 w - - 0 1", false),
+        ] {
+            assert_eq!(
+                Board::from_fen(fen).unwrap().is_dead_position(),
+                dead,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        // (fen, insufficient material, material draw)
+        for (fen, insufficient, draw) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true, true),
+            ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", true, true),
+            ("4k3/8/8/8/8/8/8/3BK3 w - - 0 1", true, true),
+            ("4k3/8/8/8/8/2b5/8/2B1K3 w - - 0 1", true, true),
+            // drawn with correct play, but not insufficient material
+            ("8/8/2k5/r7/8/8/5R2/5K2 w - - 0 1", false, true),
+            ("4k3/8/8/8/8/2b5/8/3BK3 w - - 0 1", false, true),
+            ("4k3/1n6/8/8/8/8/8/3NK3 w - - 0 1", false, true),
+            ("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1", false, false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false, false),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.is_insufficient_material(), insufficient, "{fen}");
+            assert_eq!(board.material_draw(), draw, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_game_result() {
+        for (fen, result) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                None,
+            ),
+            // back rank mates for either side
+            (
+                "3R2k1/5ppp/8/8/8/8/8/6K1 b - - 1 1",
+                Some(GameOutcome::WhiteWin),
+            ),
+            (
+                "6k1/8/8/8/8/8/5PPP/3r2K1 w - - 1 1",
+                Some(GameOutcome::BlackWin),
+            ),
+            // stalemate, the fifty move rule and insufficient material
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Some(GameOutcome::Draw)),
+            (
+                "4k3/8/8/8/8/8/4R3/4K3 b - - 100 80",
+                Some(GameOutcome::Draw),
+            ),
+            ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", Some(GameOutcome::Draw)),
+            // mate on the hundredth half move still counts
+            (
+                "3R2k1/5ppp/8/8/8/8/8/6K1 b - - 100 80",
+                Some(GameOutcome::WhiteWin),
+            ),
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().game_result(), result, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_long_shuffle() {
+        // knights shuffling back and forth never reset the clock, well past where a u8 wraps
+        let mut board = Board::from_fen("4k1n1/8/8/8/8/8/8/4K1N1 w - - 0 1").unwrap();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for (i, pair) in shuffle.iter().cycle().take(300).enumerate() {
+            board.make_move(Move::from_pair(&board, pair));
+            assert_eq!(board.halfmove_clock() as usize, i + 1);
+            let expected = (i + 1 >= 100).then_some(GameOutcome::Draw);
+            assert_eq!(board.game_result(), expected, "after {} half moves", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_bishop_material_draws() {
+        for (fen, draw) in [
+            // KBBvK with both bishops on dark squares
+            ("4k3/8/8/8/8/4B3/8/2B1K3 w - - 0 1", true),
+            // KBBvK with a bishop pair is a win
+            ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", false),
+            // KBBvKB all on dark squares
+            ("4k3/8/8/8/8/2b1B3/8/2B1K3 w - - 0 1", true),
+            // KBBvKB where white keeps the bishop pair
+            ("4k3/8/8/8/8/2b5/8/2B1KB2 w - - 0 1", false),
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().material_draw(), draw, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_piece_counts() {
+        let mut board = Board::new();
+        assert_eq!(board.piece_count(), 32);
+        for color in [White, Black] {
+            assert_eq!(board.pawn_count(color), 8);
+            assert_eq!(board.piece_count_of(color, Knight), 2);
+            assert_eq!(board.piece_count_of(color, Queen), 1);
+            assert_eq!(board.piece_count_of(color, King), 1);
+            assert_eq!(
+                board.material_count(color),
+                8 * 100 + 4 * 300 + 2 * 500 + 900
+            );
+        }
+
+        // 1. e4 d5 2. exd5 Qxd5 3. Nc3 Qxg2 4. Bxg2
+        for mv in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5g2", "f1g2"] {
+            let mv = board
+                .legal_moves()
+                .inner()
+                .iter()
+                .find(|m| m.mv.coords() == mv)
+                .unwrap()
+                .mv;
+            board.make_move(mv);
+        }
+        assert_eq!(board.piece_count(), 28);
+        assert_eq!(board.pawn_count(White), 6);
+        assert_eq!(board.pawn_count(Black), 7);
+        assert_eq!(board.piece_count_of(Black, Queen), 0);
+        assert_eq!(
+            board.material_count(White),
+            6 * 100 + 4 * 300 + 2 * 500 + 900
+        );
+        assert_eq!(board.material_count(Black), 7 * 100 + 4 * 300 + 2 * 500);
+    }
+
+    #[test]
+    fn test_cannot_win() {
+        for (fen, white, black) in [
+            // KvK
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true, true),
+            // KNvKR
+            ("4k3/8/8/8/8/8/r7/1N2K3 w - - 0 1", true, false),
+            // KBvKP
+            ("4k3/p7/8/8/8/8/8/2B1K3 w - - 0 1", true, false),
+            // KvKBN
+            ("2b1k1n1/8/8/8/8/8/8/4K3 w - - 0 1", true, false),
+            // KBvKN
+            ("4kn2/8/8/8/8/8/8/2B1K3 b - - 0 1", true, true),
+            // KPvK
+            ("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", false, true),
+            // KRvK
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false, true),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.cannot_win(White), white, "{fen}");
+            assert_eq!(board.cannot_win(Black), black, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_fen_en_passant_hash() {
+        // no black pawn can capture on e3
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        let no_ep =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let mut played = Board::new();
+        played.make_move(Move::new(Pawn, Square::E2, Square::E4, Pawn));
+
+        assert_eq!(board.en_passant_square(), None);
+        assert_eq!(board.hash(), no_ep.hash());
+        assert_eq!(board.hash(), played.hash());
+
+        // the d4 pawn can, so the square is kept
+        let ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let no_ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(ep.en_passant_square(), Some(Square::E3));
+        assert_ne!(ep.hash(), no_ep.hash());
+    }
+
+    #[test]
+    fn test_en_passant_square() {
+        let mut board = Board::new();
+        board.make_move(Move::new(Pawn, Square::E2, Square::E4, Pawn));
+        // no black pawn can capture on e3
+        assert_eq!(board.en_passant_square(), None);
+
+        let mut board = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        board.make_move(Move::new(Pawn, Square::E2, Square::E4, Pawn));
+        assert_eq!(board.en_passant_square(), Some(Square::E3));
+
+        board.make_move(Move::new(King, Square::E8, Square::D8, Pawn));
+        assert_eq!(board.en_passant_square(), None);
+    }
+
+    #[test]
+    fn test_mailbox_in_sync() {
+        fn walk(board: &Board, depth: usize) {
+            assert!(
+                board.mailbox_in_sync(),
+                "Mailbox out of sync: {}",
+                board.fen()
+            );
+            if depth == 0 {
+                return;
+            }
+            for mv in board.legal_move_list() {
+                let mut new = *board;
+                new.make_move(mv);
+                walk(&new, depth - 1);
+            }
+        }
+
+        // castling, promotions and en passant, including 960 castling with the king
+        // or rook already on its target square
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+        ] {
+            walk(&Board::from_fen(fen).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn test_shredder_castling() {
+        // 960 start position with the king on the d-file
+        let fen = "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w HAha - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.fen(), fen);
+
+        // X-FEN letters pick the outermost rook on each side
+        let xfen = Board::from_fen("rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1");
+        assert_eq!(xfen.unwrap().hash(), board.hash());
+        let inner = Board::from_fen("1r4kr/8/8/8/8/8/8/1R4KR w Kk - 0 1").unwrap();
+        assert_eq!(inner.fen(), "1r4kr/8/8/8/8/8/8/1R4KR w Hh - 0 1");
+
+        // the standard position keeps classic notation either way
+        let startpos = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1";
+        assert_eq!(Board::from_fen(startpos).unwrap().fen(), Board::new().fen());
+
+        // mixed notation
+        assert!(
+            Board::from_fen("rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KAha - 0 1").is_none()
+        );
+
+        // rights without a rook to castle with are dropped, and the rest of the FEN still parses
+        for (fen, parsed) in [
+            // no rook on the named file
+            (
+                "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w GAha - 0 1",
+                "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w Aha - 0 1",
+            ),
+            // no rook on the queenside at all
+            (
+                "4k3/8/8/8/8/8/8/4K2R w Q - 0 1",
+                "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+            ),
+            // no rooks anywhere
+            (
+                "4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1",
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            ),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.fen(), parsed);
+            assert_eq!(board.hash(), Board::from_fen(parsed).unwrap().hash());
+        }
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let occupied = board.white_pieces | board.black_pieces;
+        let squares = |attackers: BitBoard| {
+            let mut squares: Vec<Square> = attackers.squares().collect();
+            squares.sort_by_key(|sq| **sq);
+            squares
+        };
+
+        assert_eq!(
+            squares(board.attackers_to(Square::D5, White, occupied)),
+            [Square::C3, Square::E4]
+        );
+        assert_eq!(
+            squares(board.attackers_to(Square::D5, Black, occupied)),
+            [Square::B6, Square::E6, Square::F6]
+        );
+        // the queen behind the e4 pawn joins in once the pawn has captured
+        assert_eq!(
+            squares(board.attackers_to(Square::D5, White, occupied ^ Square::E4.bitboard())),
+            [Square::C3, Square::F3, Square::E4]
+        );
+        assert!(board.attackers_to(Square::A5, White, occupied).is_empty());
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let gives_check = |fen: &str, pair: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            board.gives_check(Move::from_pair(&board, pair))
+        };
+
+        // direct
+        assert!(gives_check("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"));
+        assert!(!gives_check("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a7"));
+        assert!(!gives_check("4k3/8/8/8/8/8/8/4K1N1 w - - 0 1", "g1f3"));
+        assert!(gives_check("4k3/8/8/8/8/5N2/8/4K3 w - - 0 1", "f3d6"));
+        assert!(!gives_check("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1", "d5d6"));
+        assert!(gives_check("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1", "d6d7"));
+        // discovered, by moving off the line and by capturing off it
+        assert!(gives_check("4k3/8/8/8/4N3/8/8/K3R3 w - - 0 1", "e4c5"));
+        assert!(!gives_check("4k3/8/8/3p4/8/8/6B1/K5B1 w - - 0 1", "g2d5"));
+        assert!(gives_check("7k/8/8/8/3B4/8/1Q6/K7 w - - 0 1", "d4e3"));
+        // en passant clearing a rank for the rook or a diagonal for the bishop
+        assert!(gives_check("8/8/8/R2pP2k/8/8/8/K7 w - d6 0 1", "e5d6"));
+        assert!(!gives_check("8/8/8/R1PpP2k/8/8/8/K7 w - d6 0 1", "e5d6"));
+        assert!(gives_check("8/k7/8/2pP4/8/4B3/8/7K w - c6 0 1", "d5c6"));
+        // promotions, including underpromotions and checks along the promotion rank
+        assert!(gives_check("7k/3P4/8/8/8/8/8/4K3 w - - 0 1", "d7d8q"));
+        assert!(gives_check("7k/3P4/8/8/8/8/8/4K3 w - - 0 1", "d7d8r"));
+        assert!(!gives_check("7k/3P4/8/8/8/8/8/4K3 w - - 0 1", "d7d8b"));
+        assert!(!gives_check("8/3P4/5k2/8/8/8/8/4K3 w - - 0 1", "d7d8n"));
+        assert!(gives_check("8/3P1k2/8/8/8/8/8/4K3 w - - 0 1", "d7d8n"));
+        // castling rook
+        assert!(gives_check("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+        assert!(!gives_check("3k4/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+        assert!(gives_check("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"));
+
+        // agrees with making the move on every legal move two plies into a few busy positions
+        fn agrees(board: &Board, depth: usize) {
+            for smv in &board.legal_moves() {
+                let mut after = *board;
+                after.make_move(smv.mv);
+                assert_eq!(
+                    board.gives_check(smv.mv),
+                    after.in_check(),
+                    "{} {}",
+                    board.fen(),
+                    smv.mv
+                );
+                if depth > 1 {
+                    agrees(&after, depth - 1);
+                }
+            }
+        }
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            agrees(&Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        let san = |fen: &str, pair: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            board.move_to_san(Move::from_pair(&board, pair))
+        };
+
+        let start = Board::new().fen();
+        assert_eq!(san(&start, "e2e4"), "e4");
+        assert_eq!(san(&start, "g1f3"), "Nf3");
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san(kiwipete, "e1g1"), "O-O");
+        assert_eq!(san(kiwipete, "e1c1"), "O-O-O");
+        assert_eq!(san(kiwipete, "d5e6"), "dxe6");
+        assert_eq!(san(kiwipete, "e2a6"), "Bxa6");
+        assert_eq!(san(kiwipete, "c3b1"), "Nb1");
+        assert_eq!(san(kiwipete, "f3f7"), "Qxf7+");
+        assert_eq!(san(kiwipete, "e5f7"), "Nxf7");
+
+        // disambiguation by file, by rank and by both
+        assert_eq!(san("k7/8/8/8/8/8/K7/R6R w - - 0 1", "h1d1"), "Rhd1");
+        assert_eq!(san("7R/k7/8/8/8/8/8/1K5R w - - 0 1", "h1h4"), "R1h4");
+        assert_eq!(san("k7/8/8/8/8/2Q1Q3/8/4Q1K1 w - - 0 1", "e3d2"), "Qe3d2");
+
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+        assert_eq!(san("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8n"), "exd8=N");
+        assert_eq!(san("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), "e8=Q+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"), "O-O-O");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+        assert_eq!(san("1r4kr/8/8/8/8/8/8/1RK4R w HBhb - 0 1", "c1h1"), "O-O");
+    }
+
+    #[test]
+    fn test_frc_fen_round_trip() {
+        let round_trip = |board: &Board| {
+            let fen = board.fen();
+            let parsed = Board::from_fen(&fen).unwrap_or_else(|| panic!("unparsable {fen}"));
+            assert_eq!(parsed.hash(), board.hash(), "{fen}");
+            assert_eq!(parsed.fen(), fen);
+        };
+
+        // a cheap deterministic generator, to play some moves that lose castling rights
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for n in 0..960 {
+            let mut board = Board::frc(n);
+            round_trip(&board);
+
+            for _ in 0..12 {
+                let moves = board.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                board.make_move(moves[seed as usize % moves.len()]);
+                round_trip(&board);
+            }
+        }
+    }
+
+    #[test]
+    fn test_side_to_move_swapped() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 7 1")
+                .unwrap();
+        let swapped = board.with_side_to_move_swapped();
+        assert_eq!(
+            swapped.fen(),
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 7 1"
+        );
+        assert_eq!(
+            swapped.hash(),
+            Board::from_fen(swapped.fen()).unwrap().hash()
+        );
+        assert_eq!(
+            swapped.legal_moves().len(),
+            Board::from_fen(swapped.fen()).unwrap().legal_moves().len()
+        );
+
+        let twice = swapped.with_side_to_move_swapped();
+        assert_eq!(twice.hash(), board.hash());
+        assert_eq!(twice.fen(), board.fen());
+
+        // the en passant square goes, so swapping back gives the position without it
+        let ep = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let swapped = ep.with_side_to_move_swapped();
+        assert_eq!(swapped.fen(), "4k3/8/8/3pP3/8/8/8/4K3 b - - 0 1");
+        assert_eq!(
+            swapped.with_side_to_move_swapped().hash(),
+            Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1")
+                .unwrap()
+                .hash()
+        );
+
+        // swapping away from check gives a position where the king can be taken
+        let check = Board::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert!(check.with_side_to_move_swapped().illegal_position());
+    }
+
+    #[test]
+    fn test_epd_round_trip() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let ops = [("bm", "Qxf6 Qxh3"), ("id", "\"kiwipete; position 2\"")];
+        let epd = board.epd(&ops);
+        assert_eq!(
+            epd,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - \
+             bm Qxf6 Qxh3; id \"kiwipete; position 2\";"
+        );
+
+        let (parsed, parsed_ops) = Board::from_epd(&epd).unwrap();
+        assert_eq!(parsed.hash(), board.hash());
+        assert_eq!(parsed_ops["bm"], "Qxf6 Qxh3");
+        assert_eq!(parsed_ops["id"], "kiwipete; position 2");
+        assert_eq!(parsed.epd(&ops), epd);
+
+        // a suite line with the halfmove clock as an operation
+        let (board, ops) =
+            Board::from_epd("8/8/4k3/8/8/4K3/4P3/8 b - - hmvc 12; am Kd5; id \"KP.1\";").unwrap();
+        assert_eq!(board.fen(), "8/8/4k3/8/8/4K3/4P3/8 b - - 12 1");
+        assert_eq!((&*ops["am"], &*ops["id"]), ("Kd5", "KP.1"));
+
+        assert!(Board::from_epd("8/8/4k3/8/8/4K3/4P3/8 b - - id \"unterminated;").is_none());
+        assert!(Board::from_epd("8/8/4k3/8/8/4K3/4P3/8 b -").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_fen() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+
+        // the board is written out as its FEN, and reading it back gives the same position
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{fen}\""));
+        let read: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.fen(), fen);
+        assert_eq!(read.hash(), board.hash());
+
+        assert!(serde_json::from_str::<Board>("\"not a fen\"").is_err());
+    }
+
+    #[test]
+    fn test_mirror_eval() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkb1r/pp1p1ppp/5n2/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 4",
+            "6k1/5ppp/8/1P6/8/8/2r2PPP/4R1K1 b - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mirrored = board.mirror();
+            assert_eq!(mirrored.mirror().fen(), board.fen());
+            assert_eq!(
+                board.evaluate(&mut PawnHashTable::new()),
+                mirrored.evaluate(&mut PawnHashTable::new()),
+                "{fen} and {}",
+                mirrored.fen()
+            );
+        }
+
+        // flipped by hand, with castling rights and an en passant square
+        let mirrored =
+            Board::from_fen("rnbqkb1r/pp1p1ppp/5n2/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 4")
+                .unwrap()
+                .mirror();
+        let expected =
+            Board::from_fen("rnbqkbnr/ppp1pppp/8/8/2PpP3/5N2/PP1P1PPP/RNBQKB1R b KQkq e3 0 4")
+                .unwrap();
+        assert_eq!(mirrored.fen(), expected.fen());
+        assert_eq!(mirrored.hash(), expected.hash());
+    }
+
+    #[test]
+    fn test_quiets_and_captures_partition() {
+        fn walk(board: &Board, depth: usize) {
+            let mut quiets = MoveList::new();
+            let mut captures = MoveList::new();
+            board.generate_quiets_into(&mut quiets);
+            board.generate_legal_captures_into(&mut captures);
+
+            let all = board.legal_moves();
+            assert_eq!(quiets.len() + captures.len(), all.len(), "{}", board.fen());
+            for mv in &all {
+                assert!(
+                    quiets.contains(mv.mv) != captures.contains(mv.mv),
+                    "{} in {}",
+                    mv.mv,
+                    board.fen()
+                );
+            }
+
+            if depth > 0 {
+                for mv in &all {
+                    let mut new = *board;
+                    new.make_move(mv.mv);
+                    walk(&new, depth - 1);
+                }
+            }
+        }
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            walk(&Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_has_legal_move() {
+        fn walk(board: &Board, depth: usize) {
+            let moves = board.legal_moves();
+            assert_eq!(board.has_legal_move(), !moves.is_empty(), "{}", board.fen());
+            if depth > 0 {
+                for mv in &moves {
+                    let mut new = *board;
+                    new.make_move(mv.mv);
+                    walk(&new, depth - 1);
+                }
+            }
+        }
+
+        for line in include_str!("../perftsuite.txt").lines() {
+            let fen = line.split(';').next().unwrap().trim();
+            walk(&Board::from_fen(fen).unwrap(), 2);
+        }
+
+        // checkmate, stalemate, and mate by double check
+        for fen in [
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            "k2R4/pp6/1N6/8/8/8/8/6K1 b - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(board.legal_moves().is_empty(), "{fen}");
+            assert!(!board.has_legal_move(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_unmake_move() {
+        fn perft_copy(board: &Board, depth: usize) -> usize {
+            if depth == 0 {
+                return 1;
+            }
+            let mut nodes = 0;
+            for mv in &board.legal_moves() {
+                let mut new = *board;
+                new.make_move(mv.mv);
+                nodes += perft_copy(&new, depth - 1);
+            }
+            nodes
+        }
+
+        fn perft_unmake(board: &mut Board, depth: usize) -> usize {
+            if depth == 0 {
+                return 1;
+            }
+            let mut nodes = 0;
+            for mv in &board.legal_moves() {
+                let before = format!("{board:?}");
+                let unmove = board.make_move_undoable(mv.mv);
+                nodes += perft_unmake(board, depth - 1);
+                board.unmake_move(unmove);
+                assert_eq!(format!("{board:?}"), before, "{} in {}", mv.mv, board.fen());
+            }
+            nodes
+        }
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            // FRC castling where the king or rook lands on a square the other starts on
+            "1r4kr/8/8/8/8/8/8/1RK4R w HBhb - 0 1",
+            "rk4r1/8/8/8/8/8/8/RK4R1 w GAga - 0 1",
+            "2r1kr2/8/8/8/8/8/8/2R1KR2 w FCfc - 0 1",
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_eq!(perft_unmake(&mut board, 3), perft_copy(&board, 3), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_checkers() {
+        let checkers = |fen: &str| Board::from_fen(fen).unwrap().checkers();
+
+        assert!(checkers("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_empty());
+        // single check by a slider and by a knight
+        assert_eq!(
+            checkers("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+            Square::H4.bitboard()
+        );
+        assert_eq!(
+            checkers("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1"),
+            Square::F3.bitboard()
+        );
+        // double check
+        assert_eq!(
+            checkers("k2R4/pp6/1N6/8/8/8/8/6K1 b - - 0 1"),
+            Square::B6.bitboard() | Square::D8.bitboard()
+        );
+    }
+
+    #[test]
+    fn test_move_classification() {
+        // (fen, move, capture, promotion)
+        let cases = [
+            (
+                "4k3/8/8/3pP3/8/8/2P5/4K1N1 w - d6 0 1",
+                "g1f3",
+                false,
+                false,
+            ),
+            (
+                "4k3/8/8/3pP3/8/8/2P5/4K1N1 w - d6 0 1",
+                "c2c4",
+                false,
+                false,
+            ),
+            ("4k3/8/8/3pP3/8/8/2P5/4K1N1 w - d6 0 1", "e5d6", true, false),
+            ("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8n", false, true),
+            ("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q", true, true),
+            ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", true, false),
+            ("4k3/8/8/8/8/8/8/R3K2b w Q - 0 1", "e1f1", false, false),
+            // castling is king takes own rook, but never a capture
+            ("4k3/8/8/8/8/8/8/R3K2b w Q - 0 1", "e1c1", false, false),
+        ];
+        for (fen, pair, capture, promotion) in cases {
+            let board = Board::from_fen(fen).unwrap();
+            let mv = Move::from_pair(&board, pair);
+            assert!(board.legal_moves().contains(mv), "{fen} {pair}");
+            assert_eq!(board.is_capture(mv), capture, "{fen} {pair}");
+            assert_eq!(mv.is_promotion(), promotion, "{fen} {pair}");
+            assert_eq!(board.is_tactical(mv), capture || promotion, "{fen} {pair}");
+            assert_eq!(board.is_quiet(mv), !board.is_tactical(mv), "{fen} {pair}");
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use std::cell::Cell;
+
 use super::*;
 
+use crate::move_sorting::StagedMoves;
 use crate::moves::*;
 use crate::types::*;
 use Piece::*;
@@ -16,6 +19,71 @@ impl Board {
         moves
     }
 
+    /// All legal moves in the position.
+    ///
+    /// ```
+    /// use cheers_lib::board::Board;
+    ///
+    /// let moves = Board::new().legal_moves();
+    /// assert_eq!(moves.len(), 20);
+    /// for smv in moves.inner() {
+    ///     println!("{}", smv.mv.coords());
+    /// }
+    /// ```
+    pub fn legal_moves(&self) -> MoveList {
+        let mut list = MoveList::new();
+        self.generate_legal_moves(|mvs| {
+            for mv in mvs {
+                list.push(SortingMove::new(mv))
+            }
+        });
+        list
+    }
+
+    /// Legal moves generated in stages as they're consumed: the TT move, captures that don't
+    /// lose material, killers, other quiets, then losing captures.
+    ///
+    /// ```
+    /// use cheers_lib::{board::Board, moves::Move};
+    ///
+    /// let board = Board::new();
+    /// let moves = board.staged_moves(Move::null(), [Move::null(); 2]);
+    /// assert_eq!(moves.count(), 20);
+    /// ```
+    pub fn staged_moves(&self, tt_move: Move, killers: [Move; 2]) -> StagedMoves<'_> {
+        StagedMoves::new(self, tt_move, killers)
+    }
+
+    /// Legal captures and promotions in the position.
+    pub fn legal_captures(&self) -> MoveList {
+        let mut list = MoveList::new();
+        self.generate_legal_captures_into(&mut list);
+        list
+    }
+
+    // find the legal move matching a coordinate move, accepting both standard and
+    // king-takes-rook castling notation
+    pub fn resolve(&self, mv: UciMove) -> Option<Move> {
+        let coords = mv.to_string();
+        self.legal_moves()
+            .inner()
+            .iter()
+            .map(|smv| smv.mv)
+            .find(|m| m.coords_960() == coords || m.coords() == coords)
+    }
+
+    // check that a sequence of moves can be legally played in order from this position
+    pub fn is_legal_line(&self, moves: &[Move]) -> bool {
+        let mut board = *self;
+        moves.iter().all(|&mv| {
+            let legal = board.legal_moves().contains(mv);
+            if legal {
+                board.make_move(mv);
+            }
+            legal
+        })
+    }
+
     pub fn generate_legal_moves_into(&self, captures: &mut MoveList, quiets: &mut MoveList) {
         captures.reset();
         quiets.reset();
@@ -34,7 +102,20 @@ impl Board {
         list.reset();
         self.generate_legal_moves(|mvs| {
             for mv in mvs {
-                if mv.promotion() != Pawn || self.is_capture(mv) {
+                if self.is_tactical(mv) {
+                    list.push(SortingMove::new(mv))
+                }
+            }
+        });
+    }
+
+    // everything generate_legal_captures_into leaves out: non-capturing, non-promoting moves
+    // including castling
+    pub fn generate_quiets_into(&self, list: &mut MoveList) {
+        list.reset();
+        self.generate_legal_moves(|mvs| {
+            for mv in mvs {
+                if self.is_quiet(mv) {
                     list.push(SortingMove::new(mv))
                 }
             }
@@ -43,13 +124,80 @@ impl Board {
 
     pub fn generate_legal_moves(&self, mut listener: impl FnMut(MoveMask)) {
         if self.black_to_move {
-            self.legal_moves::<Black, _>(&mut listener)
+            self.legal_moves_for::<Black, _>(&mut listener)
+        } else {
+            self.legal_moves_for::<White, _>(&mut listener)
+        }
+    }
+
+    // whether there's any legal move at all, for mate and stalemate detection. stops after the
+    // first piece type with a move instead of generating all of them
+    pub fn has_legal_move(&self) -> bool {
+        if self.check_mask.is_empty() {
+            // double check, only the king can move
+            let found = Cell::new(false);
+            if self.black_to_move {
+                self.legal_king_moves::<Black, InCheck, NoCastling, _>(&mut |mvs| {
+                    found.set(found.get() || mvs.moves.is_not_empty())
+                });
+            } else {
+                self.legal_king_moves::<White, InCheck, NoCastling, _>(&mut |mvs| {
+                    found.set(found.get() || mvs.moves.is_not_empty())
+                });
+            }
+            found.get()
+        } else if self.check_mask == FULL_BOARD {
+            if self.black_to_move {
+                self.has_legal_move_for::<Black, NotInCheck>()
+            } else {
+                self.has_legal_move_for::<White, NotInCheck>()
+            }
+        } else if self.black_to_move {
+            self.has_legal_move_for::<Black, InCheck>()
         } else {
-            self.legal_moves::<White, _>(&mut listener)
+            self.has_legal_move_for::<White, InCheck>()
+        }
+    }
+
+    fn has_legal_move_for<T: TypeColor, C: TypeCheck>(&self) -> bool {
+        let found = Cell::new(false);
+        let mut listener = |mvs: MoveMask| found.set(found.get() || mvs.moves.is_not_empty());
+
+        // the king is cheap to generate and rarely stuck
+        let color = if T::WHITE { 0 } else { 1 };
+        if !C::IN_CHECK && self.castling_rights[color].iter().any(|b| b.is_not_empty()) {
+            self.legal_king_moves::<T, C, Castling, _>(&mut listener);
+        } else {
+            self.legal_king_moves::<T, C, NoCastling, _>(&mut listener);
+        }
+        if found.get() {
+            return true;
+        }
+        if self.ep_mask.is_not_empty() {
+            self.legal_pawn_moves::<T, C, Ep, _>(&mut listener);
+        } else {
+            self.legal_pawn_moves::<T, C, NoEp, _>(&mut listener);
+        }
+        if found.get() {
+            return true;
+        }
+        self.legal_knight_moves::<T, C, _>(&mut listener);
+        if found.get() {
+            return true;
+        }
+        self.legal_bishop_moves::<T, C, _>(&mut listener);
+        if found.get() {
+            return true;
+        }
+        self.legal_rook_moves::<T, C, _>(&mut listener);
+        if found.get() {
+            return true;
         }
+        self.legal_queen_moves::<T, C, _>(&mut listener);
+        found.get()
     }
 
-    fn legal_moves<T: TypeColor, F: FnMut(MoveMask)>(&self, listener: &mut F) {
+    fn legal_moves_for<T: TypeColor, F: FnMut(MoveMask)>(&self, listener: &mut F) {
         if self.check_mask == FULL_BOARD {
             // no check
             if self.ep_mask.is_not_empty() {
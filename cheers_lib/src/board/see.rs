@@ -2,7 +2,7 @@ use crate::lookup_tables::*;
 use crate::moves::*;
 use crate::types::*;
 
-use cheers_bitboards::BitBoard;
+use cheers_bitboards::{BitBoard, Square};
 use Piece::*;
 
 use super::Board;
@@ -25,17 +25,14 @@ pub const MVV_LVA: [[i16; 6]; 6] = [
 ];
 
 impl Board {
+    // full static exchange evaluation of a move, in SEE_PIECE_VALUES from the moving side's
+    // perspective. `see_beats_threshold` is the cheaper test used in search, it doesn't model
+    // promotions during the recapture sequence so can disagree with this on promotion squares
     pub fn see(&self, mv: Move) -> i16 {
         let target = mv.to();
         let mut swap_list = [0i16; 32];
 
         let mut current_attacker = mv.piece();
-        let mut attacker_mask = mv.from().bitboard();
-
-        let bishops =
-            self.white_bishops | self.black_bishops | self.white_queens | self.black_queens;
-
-        let rooks = self.white_rooks | self.black_rooks | self.white_queens | self.black_queens;
 
         // simulate the first capture
         swap_list[0] = match self.piece_on(target) {
@@ -49,8 +46,7 @@ impl Board {
             swap_list[0] += SEE_PIECE_VALUES[mv.promotion()] - SEE_PIECE_VALUES[Pawn];
         }
 
-        let mut occupied = self.occupied;
-        let mut color = !self.black_to_move;
+        let mut occupied = self.occupied ^ mv.from().bitboard();
 
         // correct for en passent capture
         if mv.piece() == Pawn && mv.to().bitboard() == self.ep_mask {
@@ -59,7 +55,7 @@ impl Board {
             swap_list[0] = SEE_PIECE_VALUES[Pawn];
         }
 
-        let mut attackers = self.all_attacks_on(target, occupied);
+        let mut swap_off = SwapOff::new(self, target, occupied, !self.black_to_move);
 
         let mut i = 0;
         for _ in 1..32 {
@@ -77,54 +73,10 @@ impl Board {
                 break;
             }
 
-            // remove the attacker from the masks (perform the capture)
-            occupied ^= attacker_mask;
-
-            // consider diagonal x-rays
-            if current_attacker == Pawn || current_attacker == Bishop || current_attacker == Queen {
-                attackers |= lookup_bishop(target, occupied) & bishops;
-            }
-            // consider orthogonal x-rays
-            if current_attacker == Rook || current_attacker == Queen {
-                attackers |= lookup_rook(target, occupied) & rooks;
-            }
-
-            // remove used attacks
-            attackers &= occupied;
-            if attackers.is_empty() {
-                break;
-            }
-            attacker_mask = BitBoard::empty();
-            for p in PIECES {
-                let mask = if color {
-                    match p {
-                        Pawn => self.black_pawns,
-                        Knight => self.black_knights,
-                        Bishop => self.black_bishops,
-                        Rook => self.black_rooks,
-                        Queen => self.black_queens,
-                        King => self.black_king,
-                    }
-                } else {
-                    match p {
-                        Pawn => self.white_pawns,
-                        Knight => self.white_knights,
-                        Bishop => self.white_bishops,
-                        Rook => self.white_rooks,
-                        Queen => self.white_queens,
-                        King => self.white_king,
-                    }
-                };
-                if (attackers & mask).is_not_empty() {
-                    current_attacker = p;
-                    attacker_mask = (attackers & mask).first_square().bitboard();
-                    break;
-                }
-            }
-            if attacker_mask.is_empty() {
+            let Some(piece) = swap_off.next() else {
                 break;
-            }
-            color = !color;
+            };
+            current_attacker = piece;
         }
 
         i -= 1;
@@ -135,6 +87,34 @@ impl Board {
         swap_list[0]
     }
 
+    // the material the side to move wins by starting the exchange on `square` with its least
+    // valuable attacker, or 0 if there is nothing to capture or capturing loses material
+    pub fn see_on(&self, square: Square) -> i16 {
+        let (own, enemy) = if self.black_to_move {
+            (self.black_pieces, self.white_pieces)
+        } else {
+            (self.white_pieces, self.black_pieces)
+        };
+        if (enemy & square.bitboard()).is_empty() {
+            return 0;
+        }
+
+        let attackers = self.all_attacks_on(square, self.occupied) & own;
+        let Some((piece, from)) = PIECES.iter().find_map(|&p| {
+            let mask = attackers & self.piece_mask(self.black_to_move, p);
+            mask.is_not_empty().then(|| (p, mask.first_square()))
+        }) else {
+            return 0;
+        };
+        let promotion = if piece == Pawn && matches!(square.rank(), 0 | 7) {
+            Queen
+        } else {
+            Pawn
+        };
+
+        self.see(Move::new(piece, from, square, promotion)).max(0)
+    }
+
     pub fn see_beats_threshold(&self, mv: Move, threshold: i16) -> bool {
         // correct for ep capture
         let mut value = if mv.piece() == Pawn && mv.to().bitboard() == self.ep_mask {
@@ -155,7 +135,13 @@ impl Board {
             return false;
         }
 
-        value -= SEE_PIECE_VALUES[mv.piece()];
+        // a promoting pawn is on the square as the promoted piece
+        let moved_piece = if mv.promotion() != Pawn {
+            mv.promotion()
+        } else {
+            mv.piece()
+        };
+        value -= SEE_PIECE_VALUES[moved_piece];
 
         // if we still beat the threshold after the first recapture we succeed early
         if value >= 0 {
@@ -167,42 +153,11 @@ impl Board {
         if mv.piece() == Pawn && mv.to().bitboard() == self.ep_mask {
             occupied &= ((self.ep_mask << 8) | (self.ep_mask >> 8)).inverse();
         }
-        let mut attackers = self.all_attacks_on(mv.to(), occupied);
-
-        let bishops =
-            self.white_bishops | self.black_bishops | self.white_queens | self.black_queens;
-
-        let rooks = self.white_rooks | self.black_rooks | self.white_queens | self.black_queens;
 
         let mut color = !self.black_to_move;
+        let mut swap_off = SwapOff::new(self, mv.to(), occupied, color);
 
-        loop {
-            // remove used pieces from attackers
-            attackers &= occupied;
-
-            let (current_pieces, other_pieces) = if color {
-                (self.black_pieces, self.white_pieces)
-            } else {
-                (self.white_pieces, self.black_pieces)
-            };
-            let current_attackers = attackers & current_pieces;
-
-            // current color has no attackers left
-            if current_attackers.is_empty() {
-                break;
-            }
-
-            // find the least valuable piece to take with
-            let piece = *PIECES
-                .iter()
-                .find(|p| {
-                    let pieces = self.piece_mask(color, **p);
-                    (pieces & current_attackers).is_not_empty()
-                })
-                .expect("SEE: no current piece found!");
-
-            let piece_mask = self.piece_mask(color, piece);
-
+        while let Some(piece) = swap_off.next() {
             color = !color;
 
             // negamax the score
@@ -211,28 +166,89 @@ impl Board {
             if value >= 0 {
                 // if the last capture was with king and it would be in check then fail instead of pass
                 // from this color's perspective
-                if piece == King && (attackers & other_pieces).is_not_empty() {
+                if piece == King && swap_off.attackers_left() {
                     color = !color;
                 }
                 break;
             }
+        }
 
-            // remove the last used piece from occupied
-            occupied ^= (current_attackers & piece_mask).first_square().bitboard();
+        // pass if opponent ran out of attackers or not recapturing wins material
+        // fail if current player ran out of attackers or opponent has won material
+        color != self.black_to_move
+    }
+}
 
-            // add discovered attacks from behind sliders
+// the recaptures on one square, each side taking with its least valuable attacker in turn and
+// sliders behind the capturing pieces joining in as they are uncovered
+struct SwapOff<'a> {
+    board: &'a Board,
+    target: Square,
+    occupied: BitBoard,
+    attackers: BitBoard,
+    // the side to recapture next
+    color: bool,
+    // the last capturing piece, which stays on the board until it is recaptured
+    last: Option<(Piece, BitBoard)>,
+}
+
+impl<'a> SwapOff<'a> {
+    // `occupied` is without the piece making the first capture
+    fn new(board: &'a Board, target: Square, occupied: BitBoard, color: bool) -> Self {
+        Self {
+            board,
+            target,
+            occupied,
+            attackers: board.all_attacks_on(target, occupied),
+            color,
+            last: None,
+        }
+    }
+
+    // whether the side to recapture has anything left to do it with
+    fn attackers_left(&self) -> bool {
+        let pieces = if self.color {
+            self.board.black_pieces
+        } else {
+            self.board.white_pieces
+        };
+        (self.attackers & self.occupied & pieces).is_not_empty()
+    }
+}
+
+impl Iterator for SwapOff<'_> {
+    type Item = Piece;
+
+    fn next(&mut self) -> Option<Piece> {
+        if let Some((piece, mask)) = self.last.take() {
+            // remove the recaptured piece and add discovered attacks from behind sliders
+            self.occupied ^= mask;
+            let board = self.board;
             if matches!(piece, Pawn | Bishop | Queen) {
-                attackers |= lookup_bishop(mv.to(), occupied) & bishops;
+                let bishops = board.white_bishops
+                    | board.black_bishops
+                    | board.white_queens
+                    | board.black_queens;
+                self.attackers |= lookup_bishop(self.target, self.occupied) & bishops;
             }
-
             if matches!(piece, Rook | Queen) {
-                attackers |= lookup_rook(mv.to(), occupied) & rooks;
+                let rooks =
+                    board.white_rooks | board.black_rooks | board.white_queens | board.black_queens;
+                self.attackers |= lookup_rook(self.target, self.occupied) & rooks;
             }
         }
-
-        // pass if opponent ran out of attackers or not recapturing wins material
-        // fail if current player ran out of attackers or opponent has won material
-        color != self.black_to_move
+        // remove used pieces from attackers
+        self.attackers &= self.occupied;
+
+        // find the least valuable piece to take with
+        let (piece, mask) = PIECES.iter().find_map(|&p| {
+            let mask = self.attackers & self.board.piece_mask(self.color, p);
+            mask.is_not_empty()
+                .then(|| (p, mask.first_square().bitboard()))
+        })?;
+        self.last = Some((piece, mask));
+        self.color = !self.color;
+        Some(piece)
     }
 }
 
@@ -240,6 +256,8 @@ impl Board {
 mod tests {
     use std::error::Error;
 
+    use cheers_bitboards::Square;
+
     use crate::{board::Board, moves::Move};
 
     #[test]
@@ -585,6 +603,12 @@ mod tests {
             ("r2n3r/2P1P3/4N3/1k6/8/8/8/4K3 w - - 0 1", "e6d8", 300),
             ("8/8/8/1k6/6b1/4N3/2p3K1/3n4 w - - 0 1", "e3d1", -800),
             ("8/8/1k6/8/8/2N1N3/4p1K1/3n4 w - - 0 1", "c3d1", 100),
+            // rook takes defended pawn
+            ("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "e1e5", -400),
+            // queen takes guarded knight
+            ("4k3/8/3p4/4n3/8/8/8/4QK2 w - - 0 1", "e1e5", -600),
+            // doubled rooks win the x-ray exchange
+            ("4k3/4r3/8/4p3/8/8/4R3/4RK2 w - - 0 1", "e2e5", 100),
         ];
         for (fen, move_, score) in test_cases {
             let game = Board::from_fen(fen).unwrap();
@@ -613,4 +637,66 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_see_threshold_ep_and_promotion() {
+        let test_cases = [
+            // en passant, the captured pawn isn't on the target square
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("2kr4/8/8/8/2pP4/8/3K4/5Q2 b - d3 0 1", "c4d3", 100),
+            (
+                "3q2nk/pb1r1p2/np6/3P2Pp/2p1P3/2R4B/PQ3P1P/3R2K1 w - h6 0 1",
+                "g5h6",
+                0,
+            ),
+            // promotions, recaptures take the promoted piece
+            ("3n3r/2P5/8/1k6/8/8/3Q4/4K3 w - - 0 1", "c7d8q", 700),
+            (
+                "5k2/p2P2pp/8/1pb5/1Nn1P1n1/6Q1/PPP4P/R3K1NR w KQ - 0 1",
+                "d7d8q",
+                800,
+            ),
+            (
+                "r4k2/p2P2pp/8/1pb5/1Nn1P1n1/6Q1/PPP4P/R3K1NR w KQ - 0 1",
+                "d7d8q",
+                -100,
+            ),
+            ("7R/4bP2/8/8/1q6/3K4/5p2/4k3 w - - 0 1", "f7f8r", -100),
+        ];
+        for (fen, move_, score) in test_cases {
+            let game = Board::from_fen(fen).unwrap();
+            let mv = Move::from_pair(&game, move_);
+            assert!(
+                game.see_beats_threshold(mv, score),
+                "{fen} {move_} >= {score}"
+            );
+            assert!(
+                !game.see_beats_threshold(mv, score + 1),
+                "{fen} {move_} < {}",
+                score + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_see_on() {
+        let test_cases = [
+            // rook behind rook: the second rook makes the pawn safe to take
+            ("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", Square::D5, 100),
+            ("3r2k1/8/8/3p4/8/8/8/3R2K1 w - - 0 1", Square::D5, 0),
+            // queen behind bishop wins the knight, unless black has its own battery
+            ("1k6/6b1/8/4n3/8/8/1B6/Q3K3 w - - 0 1", Square::E5, 300),
+            ("1k6/6b1/8/4n3/8/8/1B6/4K3 w - - 0 1", Square::E5, 0),
+            ("1k5q/6b1/8/4n3/8/8/1B6/Q3K3 w - - 0 1", Square::E5, 0),
+            // own piece and empty square
+            ("1k6/6b1/8/4n3/8/8/1B6/Q3K3 w - - 0 1", Square::B2, 0),
+            ("1k6/6b1/8/4n3/8/8/1B6/Q3K3 w - - 0 1", Square::D4, 0),
+            // black to move takes the bishop with the pawn first
+            ("4k3/8/2n5/3p4/4B3/8/8/3QK3 b - - 0 1", Square::E4, 300),
+        ];
+        for (fen, square, score) in test_cases {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.see_on(square), score, "{fen} {}", square.coord());
+        }
+    }
 }
@@ -1,3 +1,4 @@
+pub mod affinity;
 pub mod board;
 pub mod move_sorting;
 pub mod hash_tables;
@@ -13,44 +13,77 @@ pub fn lookup_knight(square: Square) -> BitBoard {
     }
 }
 
+// sliders are looked up with pext when the build targets bmi2 (e.g. -Ctarget-cpu=native) and
+// with magics otherwise. checking for bmi2 at runtime would cost more than pext saves
 #[inline(always)]
 pub fn lookup_bishop(square: Square, mask: BitBoard) -> BitBoard {
-    #[cfg(debug_assertions)]
-    {
-        SLIDING_ATTACK_TABLE[bishop_attack_index(square, mask)]
-    }
-    #[cfg(not(debug_assertions))]
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2"))]
     unsafe {
-        *SLIDING_ATTACK_TABLE.get_unchecked(bishop_attack_index(square, mask))
+        lookup_bishop_pext(square, mask)
     }
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2")))]
+    lookup_bishop_magic(square, mask)
 }
 
 #[inline(always)]
 pub fn lookup_rook(square: Square, mask: BitBoard) -> BitBoard {
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2"))]
+    unsafe {
+        lookup_rook_pext(square, mask)
+    }
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2")))]
+    lookup_rook_magic(square, mask)
+}
+
+#[inline(always)]
+pub fn lookup_queen(square: Square, mask: BitBoard) -> BitBoard {
+    lookup_rook(square, mask) | lookup_bishop(square, mask)
+}
+
+// whichever path isn't compiled in is still checked against the other in the tests
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "bmi2"), allow(dead_code))]
+#[inline(always)]
+fn lookup_bishop_magic(square: Square, mask: BitBoard) -> BitBoard {
     #[cfg(debug_assertions)]
     {
-        SLIDING_ATTACK_TABLE[rook_attack_index(square, mask)]
+        SLIDING_ATTACK_TABLE[bishop_attack_index(square, mask)]
     }
     #[cfg(not(debug_assertions))]
     unsafe {
-        *SLIDING_ATTACK_TABLE.get_unchecked(rook_attack_index(square, mask))
+        *SLIDING_ATTACK_TABLE.get_unchecked(bishop_attack_index(square, mask))
     }
 }
 
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "bmi2"), allow(dead_code))]
 #[inline(always)]
-pub fn lookup_queen(square: Square, mask: BitBoard) -> BitBoard {
+fn lookup_rook_magic(square: Square, mask: BitBoard) -> BitBoard {
     #[cfg(debug_assertions)]
     {
         SLIDING_ATTACK_TABLE[rook_attack_index(square, mask)]
-            | SLIDING_ATTACK_TABLE[bishop_attack_index(square, mask)]
     }
     #[cfg(not(debug_assertions))]
     unsafe {
         *SLIDING_ATTACK_TABLE.get_unchecked(rook_attack_index(square, mask))
-            | *SLIDING_ATTACK_TABLE.get_unchecked(bishop_attack_index(square, mask))
     }
 }
 
+// callers must make sure the cpu has bmi2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+#[cfg_attr(not(target_feature = "bmi2"), allow(dead_code))]
+#[inline]
+unsafe fn lookup_bishop_pext(square: Square, mask: BitBoard) -> BitBoard {
+    *PEXT_ATTACK_TABLE.get_unchecked(bishop_attack_index_pext(square, mask))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+#[cfg_attr(not(target_feature = "bmi2"), allow(dead_code))]
+#[inline]
+unsafe fn lookup_rook_pext(square: Square, mask: BitBoard) -> BitBoard {
+    *PEXT_ATTACK_TABLE.get_unchecked(rook_attack_index_pext(square, mask))
+}
+
 #[inline(always)]
 pub fn lookup_king(square: Square) -> BitBoard {
     #[cfg(debug_assertions)]
@@ -63,6 +96,8 @@ pub fn lookup_king(square: Square) -> BitBoard {
     }
 }
 
+/// The squares strictly between `a` and `b`, excluding both ends. Empty when the squares don't
+/// share a rank, file or diagonal, or are adjacent.
 #[inline(always)]
 pub fn lookup_between(a: Square, b: Square) -> BitBoard {
     #[cfg(debug_assertions)]
@@ -77,6 +112,71 @@ pub fn lookup_between(a: Square, b: Square) -> BitBoard {
     }
 }
 
+/// The whole rank, file or diagonal through `a` and `b` from edge to edge, both included. Empty
+/// when the squares aren't aligned or are the same square.
+pub fn lookup_line(a: Square, b: Square) -> BitBoard {
+    let ends = a.bitboard() | b.bitboard();
+    for lookup in [lookup_bishop, lookup_rook] {
+        let from_a = lookup(a, BitBoard::empty());
+        if (from_a & b.bitboard()).is_not_empty() {
+            return (from_a & lookup(b, BitBoard::empty())) | ends;
+        }
+    }
+    BitBoard::empty()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    // (file, rank) step, north being towards the 8th rank and east towards the h-file
+    pub fn offset(&self) -> (i8, i8) {
+        match self {
+            Direction::North => (0, 1),
+            Direction::NorthEast => (1, 1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, -1),
+            Direction::South => (0, -1),
+            Direction::SouthWest => (-1, -1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, 1),
+        }
+    }
+}
+
+/// The squares from `from` to the edge of the board in `direction`, excluding `from` itself.
+/// This is what a slider on `from` attacks on an empty board in that direction.
+pub fn lookup_ray(from: Square, direction: Direction) -> BitBoard {
+    let (file_step, rank_step) = direction.offset();
+    let mut ray = BitBoard::empty();
+    let mut square = from;
+    while let Some(next) = square.offset_checked(file_step, rank_step) {
+        ray |= next.bitboard();
+        square = next;
+    }
+    ray
+}
+
 pub fn adjacent_files(file: usize) -> BitBoard {
     match file {
         0 => B_FILE,
@@ -90,3 +190,83 @@ pub fn adjacent_files(file: usize) -> BitBoard {
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::{BitBoard, Square};
+
+    use super::{lookup_between, lookup_line, lookup_queen, lookup_ray, Direction};
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_pext_matches_magics() {
+        use super::{lookup_bishop_magic, lookup_bishop_pext, lookup_rook_magic, lookup_rook_pext};
+
+        if !is_x86_feature_detected!("bmi2") {
+            return;
+        }
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for square in (0..64u8).map(Square::from) {
+            for _ in 0..1000 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                // dense and sparse boards
+                for occupied in [BitBoard(seed), BitBoard(seed & seed.rotate_left(17))] {
+                    unsafe {
+                        assert_eq!(
+                            lookup_bishop_pext(square, occupied),
+                            lookup_bishop_magic(square, occupied)
+                        );
+                        assert_eq!(
+                            lookup_rook_pext(square, occupied),
+                            lookup_rook_magic(square, occupied)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_between_and_lines() {
+        let diagonal = [
+            Square::B2,
+            Square::C3,
+            Square::D4,
+            Square::E5,
+            Square::F6,
+            Square::G7,
+        ]
+        .iter()
+        .fold(BitBoard::empty(), |bb, sq| bb | sq.bitboard());
+        assert_eq!(lookup_between(Square::A1, Square::H8), diagonal);
+        assert_eq!(lookup_between(Square::H8, Square::A1), diagonal);
+        assert!(lookup_between(Square::A1, Square::B3).is_empty());
+        assert!(lookup_between(Square::A1, Square::B2).is_empty());
+
+        let long_diagonal = diagonal | Square::A1.bitboard() | Square::H8.bitboard();
+        assert_eq!(lookup_line(Square::C3, Square::E5), long_diagonal);
+        assert_eq!(lookup_line(Square::E1, Square::E4).count_ones(), 8);
+        assert!(lookup_line(Square::A1, Square::B3).is_empty());
+
+        for from in (0..64u8).map(Square::from) {
+            // the rays in all directions make up an empty board queen's attacks
+            let rays = Direction::ALL
+                .iter()
+                .fold(BitBoard::empty(), |bb, &dir| bb | lookup_ray(from, dir));
+            assert_eq!(rays, lookup_queen(from, BitBoard::empty()));
+
+            for to in lookup_queen(from, BitBoard::empty()) {
+                let line = lookup_line(from, to);
+                assert_eq!(line & lookup_between(from, to), lookup_between(from, to));
+                assert_eq!(line, lookup_line(to, from));
+            }
+        }
+        assert_eq!(
+            lookup_ray(Square::D4, Direction::NorthEast),
+            lookup_between(Square::D4, Square::H8) | Square::H8.bitboard()
+        );
+        assert!(lookup_ray(Square::H4, Direction::East).is_empty());
+    }
+}
@@ -137,3 +137,219 @@ impl<M: TypeMoveGen> MoveSorter<M> {
         None
     }
 }
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum StagedMovesStage {
+    TTMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+// legal moves in roughly the order a search wants them: the TT move, captures that don't lose
+// material by SEE, killers, the other quiets, then losing captures. each list is only generated
+// once its stage is reached. unlike `MoveSorter` this needs no thread data, the killers are
+// passed in and the quiets come unsorted
+pub struct StagedMoves<'a> {
+    board: &'a Board,
+    stage: StagedMovesStage,
+    tt_move: Move,
+    killers: [Move; 2],
+    killer_index: usize,
+    captures: MoveList,
+    capture_index: usize,
+    quiets: MoveList,
+    quiet_index: usize,
+}
+
+impl<'a> StagedMoves<'a> {
+    pub fn new(board: &'a Board, tt_move: Move, killers: [Move; 2]) -> Self {
+        Self {
+            board,
+            stage: StagedMovesStage::TTMove,
+            tt_move,
+            killers,
+            killer_index: 0,
+            captures: MoveList::new(),
+            capture_index: 0,
+            quiets: MoveList::new(),
+            quiet_index: 0,
+        }
+    }
+
+    // TT moves and killers can come from other positions, so are checked before being yielded
+    fn is_legal(&self, mv: Move) -> bool {
+        if mv.is_null() || !self.board.is_pseudolegal(mv) {
+            return false;
+        }
+        let mut new = *self.board;
+        new.make_move(mv);
+        !new.illegal_position()
+    }
+}
+
+impl Iterator for StagedMoves<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        // a consumer cutting off on the TT move never generates anything
+        if self.stage == StagedMovesStage::TTMove {
+            self.stage = StagedMovesStage::GenerateCaptures;
+            if self.is_legal(self.tt_move) {
+                return Some(self.tt_move);
+            }
+        }
+
+        if self.stage == StagedMovesStage::GenerateCaptures {
+            self.stage = StagedMovesStage::GoodCaptures;
+            self.board.generate_legal_captures_into(&mut self.captures);
+            for i in 0..self.captures.len() {
+                *self.captures.score(i) = self.board.see(self.captures[i]) as i32;
+            }
+        }
+
+        if self.stage == StagedMovesStage::GoodCaptures {
+            while self.capture_index < self.captures.len() {
+                let (mv, score) = self.captures.pick_move(self.capture_index);
+                if score < 0 {
+                    break;
+                }
+                self.capture_index += 1;
+                if mv != self.tt_move {
+                    return Some(mv);
+                }
+            }
+            self.stage = StagedMovesStage::Killers;
+        }
+
+        if self.stage == StagedMovesStage::Killers {
+            while self.killer_index < self.killers.len() {
+                let killer = self.killers[self.killer_index];
+                self.killer_index += 1;
+                // both slots can hold the same move, which should only be tried once
+                if killer != self.tt_move
+                    && !self.killers[..self.killer_index - 1].contains(&killer)
+                    && self.board.is_quiet(killer)
+                    && self.is_legal(killer)
+                {
+                    return Some(killer);
+                }
+            }
+            self.stage = StagedMovesStage::Quiets;
+            self.board.generate_quiets_into(&mut self.quiets);
+        }
+
+        if self.stage == StagedMovesStage::Quiets {
+            while self.quiet_index < self.quiets.len() {
+                let mv = self.quiets[self.quiet_index];
+                self.quiet_index += 1;
+                if mv != self.tt_move && !self.killers.contains(&mv) {
+                    return Some(mv);
+                }
+            }
+            self.stage = StagedMovesStage::BadCaptures;
+        }
+
+        if self.stage == StagedMovesStage::BadCaptures {
+            while self.capture_index < self.captures.len() {
+                let (mv, _) = self.captures.pick_move(self.capture_index);
+                self.capture_index += 1;
+                if mv != self.tt_move {
+                    return Some(mv);
+                }
+            }
+            self.stage = StagedMovesStage::Done;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::Square;
+
+    use super::{MoveSorter, StagedMoves};
+    use crate::{
+        board::Board,
+        moves::{Move, KILLER_MOVE_SCORE},
+        thread_data::ThreadData,
+        types::{All, Piece::*},
+    };
+
+    #[test]
+    fn test_killers_only_boost_legal_moves() {
+        // the history tables are built on the stack before being boxed
+        std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(killers_only_boost_legal_moves)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn killers_only_boost_legal_moves() {
+        // killers are stored per ply, so they can come from a sibling where they were legal.
+        // they only lift the score of generated moves and are never yielded on their own
+        let board = Board::from_fen("4k3/8/8/8/8/5P2/8/4K1N1 w - - 0 1").unwrap();
+        let mut thread_data = ThreadData::new();
+        let killers = &mut thread_data.search_stack[0].killer_moves;
+        // blocked by our own pawn on f3
+        let illegal = Move::new(Knight, Square::G1, Square::F3, Pawn);
+        let legal = Move::new(Knight, Square::G1, Square::H3, Pawn);
+        killers.push(illegal);
+        killers.push(legal);
+
+        let mut sorter = MoveSorter::<All>::new(Move::null());
+        let mut yielded = Vec::new();
+        while let Some((mv, score)) = sorter.next(&board, &mut thread_data, 0) {
+            yielded.push((mv, score));
+        }
+        assert_eq!(yielded.len(), board.legal_moves().len());
+        assert!(yielded.iter().all(|&(mv, _)| mv != illegal));
+        assert!(yielded
+            .iter()
+            .any(|&(mv, score)| mv == legal && score >= KILLER_MOVE_SCORE));
+    }
+
+    #[test]
+    fn test_staged_moves() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let tt_move = Move::new(Queen, Square::F3, Square::F6, Pawn);
+        let killer = Move::new(Knight, Square::C3, Square::A4, Pawn);
+        // a killer from a sibling that's impossible here
+        let stale_killer = Move::new(Knight, Square::G1, Square::F3, Pawn);
+
+        let staged: Vec<Move> = StagedMoves::new(&board, tt_move, [stale_killer, killer]).collect();
+        let legal = board.legal_moves();
+        assert_eq!(staged.len(), legal.len());
+        assert!(legal.inner().iter().all(|smv| staged.contains(&smv.mv)));
+
+        assert_eq!(staged[0], tt_move);
+        let killer_index = staged.iter().position(|&mv| mv == killer).unwrap();
+        let captures = staged[1..killer_index].iter();
+        assert!(captures.clone().all(|&mv| board.see(mv) >= 0));
+        // the losing captures come after all the quiets. the TT move is one too
+        let losing = 1 + staged[1..]
+            .iter()
+            .position(|&mv| board.is_capture(mv) && board.see(mv) < 0)
+            .unwrap();
+        assert!(staged[losing..]
+            .iter()
+            .all(|&mv| board.is_capture(mv) && board.see(mv) < 0));
+
+        // the same killer in both slots is only yielded once
+        let doubled: Vec<Move> = StagedMoves::new(&board, tt_move, [killer; 2]).collect();
+        assert_eq!(doubled.len(), legal.len());
+        assert_eq!(doubled.iter().filter(|&&mv| mv == killer).count(), 1);
+
+        // positions with no moves, and a bogus TT move
+        let mate = Board::from_fen("k2R4/pp6/1N6/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(StagedMoves::new(&mate, tt_move, [killer; 2]).count(), 0);
+    }
+}
@@ -1,6 +1,6 @@
-use std::{fmt::Display, ops::Index};
+use std::{fmt::Display, ops::Index, str::FromStr};
 
-use crate::{board::Board, types::*};
+use crate::{board::Board, search::SEARCH_MAX_PLY, types::*};
 use pyrrhic_rs::DtzResult;
 use Piece::*;
 
@@ -10,11 +10,15 @@ use cheers_bitboards::*;
 pub struct Move(u32);
 
 impl Move {
+    // the move from a coordinate string, taking the moving piece from the board. castling is
+    // accepted both as the king's two square move and as king-takes-rook (FRC), and comes out as
+    // king-takes-rook like the move generator's. en passant and double pushes need no flags,
+    // make_move recognises them from the squares
     pub fn from_pair<T: AsRef<str>>(board: &Board, pair: T) -> Self {
         let pair = pair.as_ref();
         let from = Square::from_coord(&pair[0..2]);
         let mut to = Square::from_coord(&pair[2..4]);
-        let promotion = match pair.chars().nth(4) {
+        let promotion = match pair.chars().nth(4).map(|c| c.to_ascii_lowercase()) {
             Some('n') => Knight,
             Some('b') => Bishop,
             Some('r') => Rook,
@@ -23,14 +27,12 @@ impl Move {
         };
 
         let piece = board.piece_on(from).unwrap_or(Pawn);
-        if piece == King {
-            // correct for castling
-            if to.file() > from.file() && to.file().abs_diff(from.file()) > 1 {
-                // kingside castling
-                to = board.castling_rights()[board.current_player()][0].first_square()
-            } else if to.file() < from.file() && to.file().abs_diff(from.file()) > 1 {
-                // queenside castling
-                to = board.castling_rights()[board.current_player()][1].first_square()
+        // a king moving two or more files can only be castling
+        if piece == King && to.rank() == from.rank() && to.file().abs_diff(from.file()) > 1 {
+            let queenside = to.file() < from.file();
+            let rook = board.castling_rights()[board.current_player()][queenside as usize];
+            if rook.is_not_empty() {
+                to = rook.first_square();
             }
         }
 
@@ -69,6 +71,14 @@ impl Move {
     pub fn is_null(&self) -> bool {
         self.0 == 0
     }
+
+    pub fn from_u32(data: u32) -> Self {
+        Self(data)
+    }
+
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
     pub fn new(piece: Piece, from: Square, to: Square, promotion: Piece) -> Self {
         Self(
             piece as u32 | ((*from as u32) << 3) | ((*to as u32) << 9) | ((promotion as u32) << 15),
@@ -86,6 +96,12 @@ impl Move {
     pub fn promotion(&self) -> Piece {
         Piece::from_u8((self.0 >> 15) as u8 & 0b111)
     }
+    // captures, castling and en passant aren't encoded: TT moves, killers and countermoves are
+    // rebuilt from the piece, squares and promotion and have to compare equal to generated
+    // moves. use `Board::is_capture`, `Board::is_tactical` and `Board::is_quiet` for those
+    pub fn is_promotion(&self) -> bool {
+        self.promotion() != Pawn
+    }
 }
 
 impl Display for Move {
@@ -107,6 +123,74 @@ impl Default for Move {
     }
 }
 
+/// A move in coordinate notation (e.g. `e7e8q`), parsed without reference to a board.
+/// Use [`Board::resolve`] to turn it into a playable [`Move`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UciMove {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<Piece>,
+}
+
+impl FromStr for UciMove {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_square(coord: &[u8]) -> Option<Square> {
+            match coord {
+                [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                    Some(Square::from((rank - b'1') * 8 + (file - b'a')))
+                }
+                _ => None,
+            }
+        }
+
+        let bytes = s.as_bytes();
+        if !(4..=5).contains(&bytes.len()) {
+            return Err(format!("Invalid move length: '{s}'"));
+        }
+        let from = parse_square(&bytes[0..2]).ok_or(format!("Invalid start square: '{s}'"))?;
+        let to = parse_square(&bytes[2..4]).ok_or(format!("Invalid target square: '{s}'"))?;
+        let promotion = match bytes.get(4) {
+            None => None,
+            Some(b'n') => Some(Knight),
+            Some(b'b') => Some(Bishop),
+            Some(b'r') => Some(Rook),
+            Some(b'q') => Some(Queen),
+            Some(_) => return Err(format!("Invalid promotion piece: '{s}'")),
+        };
+
+        Ok(Self {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+impl Display for UciMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let promo = match self.promotion {
+            Some(Knight) => "n",
+            Some(Bishop) => "b",
+            Some(Rook) => "r",
+            Some(Queen) => "q",
+            _ => "",
+        };
+        write!(f, "{}", self.from.coord() + &self.to.coord() + promo)
+    }
+}
+
+impl From<Move> for UciMove {
+    fn from(mv: Move) -> Self {
+        Self {
+            from: mv.from(),
+            to: mv.to(),
+            promotion: (mv.promotion() != Pawn).then_some(mv.promotion()),
+        }
+    }
+}
+
 pub struct MoveMask {
     pub piece: Piece,
     pub start: Square,
@@ -197,17 +281,20 @@ impl SortingMove {
     }
 }
 
+/// Maximum number of legal moves in any reachable chess position.
+pub const MAX_MOVES: usize = 218;
+
 #[derive(Copy, Clone, Debug)]
 pub struct MoveList {
     len: usize,
-    inner: [SortingMove; 218],
+    inner: [SortingMove; MAX_MOVES],
 }
 
 impl MoveList {
     pub fn new() -> Self {
         Self {
             len: 0,
-            inner: [SortingMove::new(Move::null()); 218],
+            inner: [SortingMove::new(Move::null()); MAX_MOVES],
         }
     }
 
@@ -224,10 +311,21 @@ impl MoveList {
     }
 
     pub fn push(&mut self, mv: SortingMove) {
+        debug_assert!(self.len < MAX_MOVES, "MoveList overflow");
         self.inner[self.len] = mv;
         self.len += 1;
     }
 
+    // push a move if there is room, handing it back if the list is full
+    pub fn try_push(&mut self, mv: SortingMove) -> Result<(), SortingMove> {
+        if self.len < MAX_MOVES {
+            self.push(mv);
+            Ok(())
+        } else {
+            Err(mv)
+        }
+    }
+
     pub fn reset(&mut self) {
         self.len = 0;
     }
@@ -275,11 +373,20 @@ impl Index<usize> for MoveList {
     }
 }
 
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a SortingMove;
+    type IntoIter = std::slice::Iter<'a, SortingMove>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner().iter()
+    }
+}
+
 impl Default for MoveList {
     fn default() -> Self {
         Self {
             len: 0,
-            inner: [SortingMove::new(Move::default()); 218],
+            inner: [SortingMove::new(Move::default()); MAX_MOVES],
         }
     }
 }
@@ -312,7 +419,9 @@ impl<const N: usize> Default for KillerMoves<N> {
     }
 }
 
-pub const PV_MAX_LEN: usize = 128;
+/// The maximum number of moves a `PrincipalVariation` can hold. The search never goes deeper
+/// than `SEARCH_MAX_PLY`, so a PV can never be longer than that either.
+pub const PV_MAX_LEN: usize = SEARCH_MAX_PLY;
 #[derive(Copy, Clone, Debug)]
 pub struct PrincipalVariation {
     len: usize,
@@ -343,6 +452,15 @@ impl PrincipalVariation {
     pub fn clear(&mut self) {
         self.len = 0;
     }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
     pub fn iter(&self) -> std::slice::Iter<'_, Move> {
         self.moves[..self.len].iter()
     }
@@ -351,8 +469,21 @@ impl PrincipalVariation {
         self
     }
     pub fn push(&mut self, mv: Move) {
-        self.moves[self.len] = mv;
-        self.len += 1;
+        // moves past the cap are dropped, a PV that long can't come out of the search anyway
+        if self.len < PV_MAX_LEN {
+            self.moves[self.len] = mv;
+            self.len += 1;
+        }
+    }
+    // the PV in SAN, playing it out on a copy of the position it was searched from
+    pub fn to_san(&self, start: &Board) -> String {
+        let mut board = *start;
+        let mut moves = Vec::with_capacity(self.len);
+        for &mv in self.moves() {
+            moves.push(board.move_to_san(mv));
+            board.make_move(mv);
+        }
+        moves.join(" ")
     }
 }
 impl Display for PrincipalVariation {
@@ -379,3 +510,152 @@ impl Index<usize> for PrincipalVariation {
         &self.moves[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::Square;
+
+    use crate::{board::Board, types::Piece};
+
+    use super::{Move, MoveList, PrincipalVariation, SortingMove, UciMove, MAX_MOVES, PV_MAX_LEN};
+
+    #[test]
+    fn test_max_moves() {
+        let board =
+            Board::from_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        let mut moves = board.legal_moves();
+        assert_eq!(moves.len(), MAX_MOVES);
+        // a full list still holds distinct, legal moves
+        for (i, smv) in moves.inner().iter().enumerate() {
+            assert!(moves.inner()[..i].iter().all(|other| other.mv != smv.mv));
+            let mut after = board;
+            after.make_move(smv.mv);
+            assert!(!after.illegal_position());
+        }
+
+        let extra = moves[0];
+        assert!(moves.try_push(SortingMove::new(extra)).is_err());
+        assert_eq!(moves.len(), MAX_MOVES);
+
+        let mut empty = MoveList::new();
+        assert!(empty.try_push(SortingMove::new(extra)).is_ok());
+        assert!(empty.contains(extra));
+    }
+
+    #[test]
+    fn test_from_pair() {
+        for (fen, pairs) in [
+            // quiet, double push, capture and en passant
+            (
+                "4k3/8/8/3pP3/8/8/2P5/4K1N1 w - d6 0 1",
+                &["g1f3", "c2c4", "c2c3", "e5d6"][..],
+            ),
+            // promotion, underpromotion and capture promotions
+            (
+                "3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1",
+                &["e7e8q", "e7e8n", "e7d8r", "e7d8b", "e7d8Q"][..],
+            ),
+            // standard castling in both notations
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                &["e1g1", "e1c1", "e1h1", "e1a1"][..],
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+                &["e8g8", "e8c8"][..],
+            ),
+            // FRC castling with the king on c1, including onto the rook's own square
+            (
+                "1r4kr/8/8/8/8/8/8/1RK4R w HBhb - 0 1",
+                &["c1h1", "c1b1", "c1g1"][..],
+            ),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for pair in pairs {
+                let mv = Move::from_pair(&board, pair);
+                assert!(board.legal_moves().contains(mv), "{fen}: {pair} gave {mv}");
+            }
+        }
+
+        let board = Board::from_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Move::from_pair(&board, "e7e8n").promotion(), Piece::Knight);
+        let board = Board::from_fen("1r4kr/8/8/8/8/8/8/1RK4R w HBhb - 0 1").unwrap();
+        assert_eq!(Move::from_pair(&board, "c1g1").to(), Square::H1);
+    }
+
+    #[test]
+    fn test_uci_move_parsing() {
+        let promotion: UciMove = "e7e8q".parse().unwrap();
+        assert_eq!(
+            promotion,
+            UciMove {
+                from: Square::E7,
+                to: Square::E8,
+                promotion: Some(Piece::Queen)
+            }
+        );
+        assert_eq!(promotion.to_string(), "e7e8q");
+        assert_eq!("g1f3".parse::<UciMove>().unwrap().to_string(), "g1f3");
+
+        for invalid in ["", "0000", "e2", "e2e4x", "e2e9", "i2e4", "e7e8qq", "e7e8k"] {
+            assert!(invalid.parse::<UciMove>().is_err(), "Parsed '{invalid}'");
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let resolve = |s: &str| board.resolve(s.parse().unwrap());
+
+        let promotion = resolve("b7b8n").unwrap();
+        assert_eq!(promotion.piece(), Piece::Pawn);
+        assert_eq!(promotion.promotion(), Piece::Knight);
+        assert_eq!(UciMove::from(promotion).to_string(), "b7b8n");
+
+        // castling is accepted in both standard and king-takes-rook notation
+        assert_eq!(resolve("e1g1"), resolve("e1h1"));
+        assert!(resolve("e1g1").is_some());
+
+        assert!(resolve("b7b8").is_none());
+        assert!(resolve("e1e3").is_none());
+        assert!(resolve("e8e7").is_none());
+    }
+
+    #[test]
+    fn test_pv_to_san() {
+        let board = Board::new();
+        let mut pv = PrincipalVariation::new();
+        let mut scratch = board;
+        for pair in ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "g8f6", "e1g1"] {
+            let mv = Move::from_pair(&scratch, pair);
+            scratch.make_move(mv);
+            pv.push(mv);
+        }
+
+        assert_eq!(pv.to_string(), "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1g1");
+        assert_eq!(pv.to_san(&board), "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O");
+        assert_eq!(
+            pv.chess_960(true).to_string(),
+            "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1h1"
+        );
+    }
+
+    #[test]
+    fn test_pv_is_inline() {
+        // a PV is made at every node, so it must stay a plain stack value with no heap storage
+        fn assert_copy<T: Copy>() {}
+        assert_copy::<PrincipalVariation>();
+        assert!(!std::mem::needs_drop::<PrincipalVariation>());
+
+        let mut pv = PrincipalVariation::new();
+        for _ in 0..PV_MAX_LEN + 5 {
+            pv.push(Move::new(
+                Piece::Knight,
+                Square::G1,
+                Square::F3,
+                Piece::Pawn,
+            ));
+        }
+        assert_eq!(pv.len(), PV_MAX_LEN);
+    }
+}
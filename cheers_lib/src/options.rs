@@ -1,15 +1,31 @@
 #[derive(Clone, Copy)]
 pub struct SearchOptions {
     pub threads: usize,
+    pub allow_oversubscription: bool,
+    pub thread_affinity: bool,
+    pub eval_trace: bool,
+    pub show_wdl: bool,
+    pub san_pv: bool,
+    // root move scores are perturbed by up to this many centipawns
+    pub eval_noise: i16,
+    // skip check extensions for checks that hang material, and cap them per line
+    pub limit_check_extensions: bool,
+    pub use_aspiration: bool,
+    pub aspiration_delta: i16,
+    pub aspiration_min_depth: i8,
     pub tt_size_mb: usize,
     pub nmp_depth: i8,
     pub nmp_const_reduction: i8,
     pub nmp_linear_divisor: i8,
+    // null move cutoffs at or above this depth are verified with a normal reduced search
+    pub nmp_verification_depth: i8,
     pub see_pruning_depth: i8,
     pub see_capture_margin: i16,
     pub see_quiet_margin: i16,
     pub pvs_fulldepth: i8,
     pub delta_pruning_margin: i16,
+    // captures that give check are never delta pruned
+    pub delta_pruning_keeps_checks: bool,
     pub fp_margin_1: i16,
     pub fp_margin_2: i16,
     pub fp_margin_3: i16,
@@ -17,13 +33,20 @@ pub struct SearchOptions {
     pub rfp_margin: i16,
     pub rfp_improving_margin: i16,
     pub lmp_depth: i8,
+    // LMR reduces by base + ln(depth) * ln(move index) / divisor, both in hundredths
+    pub lmr_base: i16,
+    pub lmr_divisor: i16,
     pub history_lmr_divisor: i16,
+    pub capture_history_lmr_divisor: i16,
     pub iir_depth: i8,
+    pub tt_move_cutoff_depth: i8,
 }
 
 pub const NMP_DEPTH: i8 = 1;
 pub const NMP_CONST_REDUCTION: i8 = 3;
 pub const NMP_LINEAR_DIVISOR: i8 = 3;
+// 0 disables null move verification
+pub const NMP_VERIFICATION_DEPTH: i8 = 0;
 pub const SEE_PRUNING_DEPTH: i8 = 9;
 pub const SEE_CAPTURE_MARGIN: i16 = -54;
 pub const SEE_QUIET_MARGIN: i16 = -45;
@@ -36,22 +59,45 @@ pub const RFP_DEPTH: i8 = 16;
 pub const RFP_MARGIN: i16 = 38;
 pub const RFP_IMPROVING_MARGIN: i16 = -24;
 pub const LMP_DEPTH: i8 = 6;
+// the pregenerated LMR table is used at these values
+pub const LMR_BASE: i16 = 122;
+pub const LMR_DIVISOR: i16 = 200;
 pub const HISTORY_LMR_DIVISOR: i16 = 1161;
+// 0 disables LMR for captures
+pub const CAPTURE_HISTORY_LMR_DIVISOR: i16 = 0;
 pub const IIR_DEPTH: i8 = 4;
+pub const ASPIRATION_DELTA: i16 = 20;
+pub const ASPIRATION_MIN_DEPTH: i8 = 2;
+// most check extensions on one line with `limit_check_extensions`
+pub const MAX_CHECK_EXTENSIONS: u8 = 8;
+// 0 disables the TT move cutoff
+pub const TT_MOVE_CUTOFF_DEPTH: i8 = 0;
 
 impl Default for SearchOptions {
     fn default() -> Self {
         Self {
             threads: 1,
+            allow_oversubscription: false,
+            thread_affinity: false,
+            eval_trace: false,
+            show_wdl: false,
+            san_pv: false,
+            eval_noise: 0,
+            limit_check_extensions: false,
+            use_aspiration: true,
+            aspiration_delta: ASPIRATION_DELTA,
+            aspiration_min_depth: ASPIRATION_MIN_DEPTH,
             tt_size_mb: 8,
             nmp_depth: NMP_DEPTH,
             nmp_const_reduction: NMP_CONST_REDUCTION,
             nmp_linear_divisor: NMP_LINEAR_DIVISOR,
+            nmp_verification_depth: NMP_VERIFICATION_DEPTH,
             see_pruning_depth: SEE_PRUNING_DEPTH,
             see_capture_margin: SEE_CAPTURE_MARGIN,
             see_quiet_margin: SEE_QUIET_MARGIN,
             pvs_fulldepth: PVS_FULLDEPTH,
             delta_pruning_margin: DELTA_PRUNING_MARGIN,
+            delta_pruning_keeps_checks: false,
             fp_margin_1: FP_MARGIN_1,
             fp_margin_2: FP_MARGIN_2,
             fp_margin_3: FP_MARGIN_3,
@@ -59,8 +105,27 @@ impl Default for SearchOptions {
             rfp_margin: RFP_MARGIN,
             rfp_improving_margin: RFP_IMPROVING_MARGIN,
             lmp_depth: LMP_DEPTH,
+            lmr_base: LMR_BASE,
+            lmr_divisor: LMR_DIVISOR,
             history_lmr_divisor: HISTORY_LMR_DIVISOR,
+            capture_history_lmr_divisor: CAPTURE_HISTORY_LMR_DIVISOR,
             iir_depth: IIR_DEPTH,
+            tt_move_cutoff_depth: TT_MOVE_CUTOFF_DEPTH,
+        }
+    }
+}
+
+impl SearchOptions {
+    // `UCI_AnalyseMode`: turn off null move pruning, reverse futility pruning, late move pruning
+    // and SEE pruning, so the score is closer to the plain alpha-beta value at the cost of a
+    // much slower search
+    pub fn analyse_mode(self) -> Self {
+        Self {
+            nmp_depth: i8::MAX,
+            rfp_depth: 0,
+            lmp_depth: 0,
+            see_pruning_depth: 0,
+            ..self
         }
     }
 }
@@ -1,13 +1,14 @@
 use std::sync::atomic::*;
 use std::sync::{atomic::Ordering::*, Arc, RwLock};
 use std::thread;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use cheers_bitboards::BitBoard;
 use cheers_pregen::{LMP_MARGINS, LMR};
 use eval_params::{CHECKMATE_SCORE, DRAW_SCORE};
 use pyrrhic_rs::{DtzProbeValue, TableBases, WdlProbeResult};
 
+use crate::affinity::{OsPinner, ThreadPinner};
 use crate::board::see::SEE_PIECE_VALUES;
 use crate::moves::*;
 use crate::thread_data::ThreadData;
@@ -16,8 +17,8 @@ use crate::{
     board::*,
     hash_tables::{score_from_tt, score_into_tt, NodeType::*, PawnHashTable, TranspositionTable},
     move_sorting::MoveSorter,
-    options::SearchOptions,
-    types::{All, Captures, NotRoot, Piece::*, Root, TypeRoot},
+    options::{SearchOptions, LMR_BASE, LMR_DIVISOR, MAX_CHECK_EXTENSIONS},
+    types::{All, Captures, Color, NotRoot, Piece::*, Root, TypeRoot},
 };
 
 use self::evaluate::TB_WIN_SCORE;
@@ -26,6 +27,8 @@ use self::tb_adapter::MovegenAdapter;
 pub static ABORT_SEARCH: AtomicBool = AtomicBool::new(false);
 pub static NODE_COUNT: AtomicUsize = AtomicUsize::new(0);
 pub static TB_HITS: AtomicUsize = AtomicUsize::new(0);
+// searches repeated after failing outside the aspiration window
+pub static ASPIRATION_RESEARCHES: AtomicUsize = AtomicUsize::new(0);
 
 pub const INF: i16 = i16::MAX;
 pub const MINUS_INF: i16 = -INF;
@@ -45,15 +48,28 @@ pub struct Search {
     pawn_hash_table: PawnHashTable,
     pub thread_data: ThreadData,
     pub max_depth: Option<usize>,
+    // hard limit, checked at every node
     pub max_nodes: Option<usize>,
+    // soft limit, only checked after each completed iteration
+    pub soft_max_nodes: Option<usize>,
     pub max_time_ms: Option<(usize, usize)>,
     pub abort_time_ms: Option<usize>,
     start_time: Instant,
     output: bool,
     chess_960: bool,
+    // see `root_in_search_history`
+    root_in_search_history: bool,
+    // picks the root move offsets for `SearchOptions::eval_noise`
+    noise_seed: u64,
+    // 0 for the main thread, helpers count up from 1
+    thread_index: usize,
     options: SearchOptions,
+    // LMR reductions by [depth][move index], from the options
+    lmr: [[i8; 64]; 64],
     pub local_nodes: usize,
     root_nodes: [[usize; 64]; 64],
+    // best move, score and depth of the last completed iteration, packed for atomic updates
+    current_best: Arc<AtomicU64>,
 }
 
 impl Search {
@@ -69,14 +85,20 @@ impl Search {
             thread_data: ThreadData::new(),
             max_depth: None,
             max_nodes: None,
+            soft_max_nodes: None,
             max_time_ms: None,
             abort_time_ms: None,
             start_time: Instant::now(),
             output: false,
             chess_960: false,
+            root_in_search_history: true,
+            noise_seed: time_seed(),
+            thread_index: 0,
             options: SearchOptions::default(),
+            lmr: LMR,
             local_nodes: 0,
             root_nodes: [[0; 64]; 64],
+            current_best: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -92,14 +114,20 @@ impl Search {
             thread_data: ThreadData::new(),
             max_depth: None,
             max_nodes: None,
+            soft_max_nodes: None,
             max_time_ms: None,
             abort_time_ms: None,
             start_time: Instant::now(),
             output: false,
             chess_960: false,
+            root_in_search_history: true,
+            noise_seed: time_seed(),
+            thread_index: 0,
             options: SearchOptions::default(),
+            lmr: LMR,
             local_nodes: 0,
             root_nodes: [[0; 64]; 64],
+            current_best: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -111,6 +139,11 @@ impl Search {
         self
     }
 
+    pub fn transposition_table(mut self, tt: Arc<RwLock<TranspositionTable>>) -> Self {
+        self.transposition_table = tt;
+        self
+    }
+
     pub fn tablebases(mut self, tablebases: Option<TableBases<MovegenAdapter>>) -> Self {
         self.tablebases = tablebases;
         self
@@ -126,30 +159,231 @@ impl Search {
         self
     }
 
+    // node limits are exact and reproducible with one thread. helpers only add their nodes to
+    // the shared count after each iteration, so with more threads the limits are approximate
     pub fn max_nodes(mut self, nodes: Option<usize>) -> Self {
         self.max_nodes = nodes;
         self
     }
 
+    pub fn soft_max_nodes(mut self, nodes: Option<usize>) -> Self {
+        self.soft_max_nodes = nodes;
+        self
+    }
+
     pub fn output(mut self, output: bool) -> Self {
         self.output = output;
         self
     }
 
+    // by default the root is part of the search history like any other node, so returning to it
+    // inside the search scores as a draw. without it the root counts towards the game history
+    // instead, and only draws once it has occurred three times in total
+    pub fn root_in_search_history(mut self, root_in_search_history: bool) -> Self {
+        self.root_in_search_history = root_in_search_history;
+        self
+    }
+
     pub fn chess_960(mut self, chess_960: bool) -> Self {
         self.chess_960 = chess_960;
         self
     }
 
+    // only matters with eval noise enabled, the seed is taken from the clock by default
+    pub fn noise_seed(mut self, noise_seed: u64) -> Self {
+        self.noise_seed = noise_seed;
+        self
+    }
+
+    // point a finished search at the next position of the game. history and killer tables are
+    // kept, so they keep accumulating from move to move like the TT does. limits are left as
+    // they were and should be set again
+    pub fn set_position(&mut self, game: Board, pre_history: Vec<u64>) {
+        self.game = game;
+        self.pre_history = pre_history;
+        self.search_history.clear();
+    }
+
+    // forget what was learned in the previous game
+    pub fn new_game(&mut self) {
+        self.thread_data = ThreadData::new();
+    }
+
+    // best move, score and completed depth so far. Clones of a search share this, so keep a
+    // clone around to poll a search running on another thread
+    pub fn current_best(&self) -> Option<(Move, i16, usize)> {
+        let data = self.current_best.load(Relaxed);
+        let mv = Move::from_u32(data as u32);
+        if mv.is_null() {
+            None
+        } else {
+            Some((mv, (data >> 32) as u16 as i16, (data >> 48) as usize))
+        }
+    }
+
+    // the game ply is approximated by the number of moves played since the position command
+    fn wdl_string(&self, score: i16) -> String {
+        if self.options.show_wdl {
+            let (win, draw, loss) = score_to_wdl(score, self.pre_history.len());
+            format!(" wdl {win} {draw} {loss}")
+        } else {
+            String::new()
+        }
+    }
+
+    // a fixed offset in [-noise, noise] per root move, so the same move is nudged the same way
+    // in every iteration and the search can still settle on a best move
+    fn root_noise(&self, mv: Move) -> i16 {
+        let noise = self.options.eval_noise as u64;
+        // splitmix64 finaliser
+        let mut z = self.noise_seed ^ (mv.to_u32() as u64).wrapping_mul(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        (z % (2 * noise + 1)) as i16 - noise as i16
+    }
+
+    // late captures are reduced by how well they have done before, so captures that keep
+    // losing get searched less
+    fn capture_reduction(
+        &self,
+        mv: Move,
+        player: Color,
+        depth: i8,
+        move_index: usize,
+        pv_node: bool,
+    ) -> i8 {
+        let mut r = self.lmr[(depth as usize).min(63)][move_index.min(63)];
+        r += !pv_node as i8;
+        let history = self.thread_data.capture_history_tables[player][mv];
+        r -= (history / self.options.capture_history_lmr_divisor) as i8;
+        r.max(0)
+    }
+
+    // coordinate notation by default, GUIs expect it
+    fn pv_string(&self, pv: &PrincipalVariation) -> String {
+        if self.options.san_pv {
+            pv.to_san(&self.game)
+        } else {
+            pv.to_string()
+        }
+    }
+
+    // a position repeated once inside the search, or twice in the game before it, is a draw.
+    // the root is always the first entry of the search history. only positions since the last
+    // irreversible move can repeat, and the halfmove clock counts those across both histories
+    fn is_repetition(&self, board: &Board) -> bool {
+        let root_len = (!self.root_in_search_history as usize).min(self.search_history.len());
+        let (root, search_history) = self.search_history.split_at(root_len);
+        let halfmove_clock = board.halfmove_clock() as usize;
+
+        search_history
+            .iter()
+            .rev()
+            .take(halfmove_clock)
+            .any(|&h| h == board.hash())
+            || self
+                .pre_history
+                .iter()
+                .chain(root)
+                .rev()
+                .take(halfmove_clock.saturating_sub(search_history.len()))
+                .filter(|&&h| h == board.hash())
+                .count()
+                >= 2
+    }
+
+    // single threaded, count this thread's own nodes so the stopping point never depends on
+    // when the shared counter was last reset
+    fn node_limit_reached(&self, shared_nodes: usize) -> bool {
+        let nodes = if self.options.threads > 1 {
+            shared_nodes
+        } else {
+            self.local_nodes - 1
+        };
+        self.max_nodes.is_some_and(|n| nodes >= n)
+    }
+
+    fn set_current_best(&self, mv: Move, score: i16, depth: usize) {
+        let data = mv.to_u32() as u64 | ((score as u16 as u64) << 32) | ((depth as u64) << 48);
+        self.current_best.store(data, Relaxed);
+    }
+
     pub fn options(mut self, options: SearchOptions) -> Self {
         self.options = options;
+        self.lmr = lmr_table(options.lmr_base, options.lmr_divisor);
         self
     }
 
-    pub fn smp_search(self) -> (i16, PrincipalVariation, Option<TableBases<MovegenAdapter>>) {
+    pub fn smp_search(&mut self) -> (i16, PrincipalVariation, Option<TableBases<MovegenAdapter>>) {
+        let (score, pv, tablebases) = self.smp_search_with_pinner(&OsPinner);
+        // an illegal PV is a search bug, caught in debug builds and repaired in release ones
+        debug_assert!(
+            self.game.is_legal_line(pv.moves()),
+            "illegal move in PV {pv} from {}",
+            self.game.fen()
+        );
+        (score, self.legal_pv(pv), tablebases)
+    }
+
+    // the PV cut off at its first illegal move, so a GUI never gets an illegal `bestmove`.
+    // if even the first move is illegal, fall back to the TT move or else any legal move
+    fn legal_pv(&self, pv: PrincipalVariation) -> PrincipalVariation {
+        let mut board = self.game;
+        let mut legal = PrincipalVariation::new().chess_960(self.chess_960);
+        for &mv in pv.iter() {
+            if board.make_move_checked(mv).is_err() {
+                break;
+            }
+            legal.push(mv);
+        }
+        if legal.len() == pv.len() {
+            return pv;
+        }
+        if self.output {
+            println!(
+                "info string illegal move in PV {pv} from {}, truncated to {legal}",
+                self.game.fen()
+            );
+        }
+        if legal.is_empty() {
+            let moves = self.game.legal_moves();
+            let tt_move = self
+                .transposition_table
+                .read()
+                .unwrap()
+                .get(self.game.hash())
+                .map(|e| Move::new(e.piece, e.move_from, e.move_to, e.promotion))
+                .filter(|&mv| moves.contains(mv));
+            if let Some(mv) = tt_move.or_else(|| (!moves.is_empty()).then(|| moves[0])) {
+                legal.push(mv);
+            }
+        }
+        legal
+    }
+
+    fn smp_search_with_pinner<P: ThreadPinner>(
+        &mut self,
+        pinner: &P,
+    ) -> (i16, PrincipalVariation, Option<TableBases<MovegenAdapter>>) {
         ABORT_SEARCH.store(false, Relaxed);
         NODE_COUNT.store(0, Ordering::Relaxed);
         TB_HITS.store(0, Ordering::Relaxed);
+        ASPIRATION_RESEARCHES.store(0, Ordering::Relaxed);
+        // the clock runs from here for searches that are reused
+        self.start_time = Instant::now();
+
+        if self.output && self.options.eval_trace {
+            let terms = self
+                .game
+                .eval_terms()
+                .map(|(name, score)| format!("{name} {}/{}", score.mg(), score.eg()))
+                .join(" ");
+            println!(
+                "info string eval (white mg/eg) {terms} phase {}",
+                self.game.game_phase()
+            );
+        }
 
         // if tablebases are available at the root, take the best move from there
         if let Some(ref tb) = self.tablebases {
@@ -180,6 +414,7 @@ impl Search {
 
                         // cloning the TB handle satisfies the borrow checker, the original
                         // will just be dropped immediately anyway
+                        self.set_current_best(tb_move, tb_score, 0);
                         return (tb_score, tb_pv, Some(tb.clone()));
                     }
                 }
@@ -187,18 +422,39 @@ impl Search {
         }
 
         // Lazy SMP: start all threads at the same depth, communicating only
-        // via the shared TT
+        // via the shared TT. helpers skip some iterations so they don't all search the same tree
         let mut score = MINUS_INF;
         let mut pv = PrincipalVariation::new();
         let mut tablebases = None;
+        let available = thread::available_parallelism().map_or(1, |n| n.get());
+        let threads = effective_threads(
+            self.options.threads,
+            available,
+            self.options.allow_oversubscription,
+        );
+        if threads < self.options.threads && self.output {
+            println!(
+                "info string {} threads requested but only {available} available, using {threads}",
+                self.options.threads
+            );
+        }
+        // each thread gets its own core, with the main thread on the first
+        let pin = self.options.thread_affinity;
         thread::scope(|s| {
             // helper threads: these only have their results added to the TT
-            for _ in 1..self.options.threads {
-                let search = self.clone();
-                s.spawn(|| {
+            for i in 1..threads {
+                let mut search = self.clone();
+                search.thread_index = i;
+                s.spawn(move || {
+                    if pin {
+                        pinner.pin_current_thread(i);
+                    }
                     let _ = search.search::<HelperThread>(true);
                 });
             }
+            if pin {
+                pinner.pin_current_thread(0);
+            }
             // main thread: this is the only thread that reports back over UCI
             (score, pv, tablebases) = self.search::<MainThread>(true);
             ABORT_SEARCH.store(true, Relaxed);
@@ -208,15 +464,32 @@ impl Search {
     }
 
     pub fn search<M: TypeMainThread>(
-        mut self,
+        &mut self,
         set_global_abort: bool,
     ) -> (i16, PrincipalVariation, Option<TableBases<MovegenAdapter>>) {
-        ABORT_SEARCH.store(false, Relaxed);
+        // helpers only stop on the abort flag, so one starting after the main thread has
+        // finished must not clear it again
         if M::MAIN_THREAD {
+            ABORT_SEARCH.store(false, Relaxed);
             NODE_COUNT.store(0, Ordering::Relaxed);
         }
         let mut last_score = i16::MIN;
         let mut last_pv = PrincipalVariation::new();
+        if M::MAIN_THREAD {
+            self.current_best.store(0, Relaxed);
+        }
+        self.local_nodes = 0;
+        self.root_nodes = [[0; 64]; 64];
+
+        // a zero time limit (`go movetime 0`) or a single legal move asks for an instant move:
+        // complete depth 1 without the clock aborting it partway through. Searches without a
+        // time limit are left alone so analysis still runs deep
+        if let Some(limits) = self.max_time_ms {
+            if limits == (0, 0) || self.game.legal_moves().len() == 1 {
+                self.max_time_ms = None;
+                self.max_depth = Some(1);
+            }
+        }
 
         // fraction of main thread nodes spent on the best move
         let mut node_fraction = 0;
@@ -226,13 +499,47 @@ impl Search {
 
         let start = Instant::now();
 
+        // `go depth 0`: the iterative deepening loop starts at depth 1, so answer with the
+        // quiescence search instead
+        if self.max_depth == Some(0) {
+            let (score, pv) = self.quiesce_root::<M>(tt);
+            if M::MAIN_THREAD {
+                if self.output {
+                    let nodes = NODE_COUNT.load(Relaxed);
+                    println!(
+                        "info depth 0 seldepth {} score cp {score}{} nodes {nodes} time {} pv {}",
+                        self.seldepth,
+                        self.wdl_string(score),
+                        start.elapsed().as_millis(),
+                        self.pv_string(&pv),
+                    );
+                }
+                if !pv.is_empty() {
+                    self.set_current_best(pv[0], score, 0);
+                }
+            }
+            if set_global_abort {
+                ABORT_SEARCH.store(true, Relaxed);
+            }
+            return (score, pv, self.tablebases.take());
+        }
+
         // Iterative Deepening: search with increasing depth, exploiting the results
         // of shallower searches to speed up deeper ones
         'id_loop: for i in 1..SEARCH_MAX_PLY {
+            if skip_depth(self.thread_index, i) {
+                continue;
+            }
+
             // Aspiration Window: search a narrow window around the score in hope of saving
             // some search time
-            let mut window_size = 20;
-            let mut window = if i == 1 {
+            let delta = self.options.aspiration_delta;
+            let mut window_size = delta;
+            let mut researches = 0;
+            let mut window = if i == 1
+                || i < self.options.aspiration_min_depth as usize
+                || !self.options.use_aspiration
+            {
                 (MINUS_INF, INF)
             } else {
                 // saturate to prevent overflows
@@ -279,6 +586,8 @@ impl Search {
                     } else {
                         format!("cp {last_score}")
                     };
+                    let wdl_string = self.wdl_string(last_score);
+                    let pv_string = self.pv_string(&last_pv);
                     let hash_fill = tt.sample_fill();
                     let nodes = if set_global_abort {
                         NODE_COUNT.load(Relaxed)
@@ -293,7 +602,7 @@ impl Search {
 
                     if M::MAIN_THREAD && self.output {
                         println!(
-                            "info depth {} seldepth {} score {score_string} nodes {} nps {} {tbhits_string} hashfull {} time {} pv {last_pv}",
+                            "info depth {} seldepth {} score {score_string}{wdl_string} nodes {} nps {} {tbhits_string} hashfull {} time {} pv {pv_string}",
                             i-1,
                             self.seldepth,
                             nodes,
@@ -310,12 +619,10 @@ impl Search {
                     // fail high, expand upper window
                     (true, false) => {
                         window = (window.0, window.1.saturating_add(window_size).min(INF));
-                        window_size = window_size.saturating_mul(2);
                     }
                     // fail low, expand lower window
                     (false, true) => {
                         window = (window.0.saturating_sub(window_size).max(-INF), window.1);
-                        window_size = window_size.saturating_mul(2);
                     }
                     // exact score within the window, search success
                     (true, true) => break score,
@@ -326,6 +633,16 @@ impl Search {
                         );
                     }
                 }
+
+                // widen gently for the first couple of failures, which are usually near misses,
+                // then double so unstable positions still reach the full window quickly
+                researches += 1;
+                window_size = if researches <= 2 {
+                    window_size.saturating_add(delta)
+                } else {
+                    window_size.saturating_mul(2)
+                };
+                ASPIRATION_RESEARCHES.fetch_add(1, Relaxed);
             };
 
             let end = Instant::now();
@@ -335,6 +652,8 @@ impl Search {
             } else {
                 format!("cp {score}")
             };
+            let wdl_string = self.wdl_string(score);
+            let pv_string = self.pv_string(&pv);
             let hash_fill = tt.sample_fill();
             let nodes = if set_global_abort {
                 NODE_COUNT.load(Relaxed)
@@ -351,7 +670,7 @@ impl Search {
             // we can trust the results from the previous search
             if M::MAIN_THREAD && self.output {
                 println!(
-                    "info depth {i} seldepth {} score {score_string} nodes {} nps {} {tbhits_string}hashfull {} time {} pv {pv}",
+                    "info depth {i} seldepth {} score {score_string}{wdl_string} nodes {} nps {} {tbhits_string}hashfull {} time {} pv {pv_string}",
                     self.seldepth,
                     nodes,
                     ((nodes) as f32 / (end - start).as_secs_f32()) as usize,
@@ -360,8 +679,17 @@ impl Search {
                 )
             };
 
+            // TT moves are only checked for pseudolegality, make sure none slip into the PV
+            debug_assert!(
+                self.game.is_legal_line(pv.moves()),
+                "Illegal move in PV: {pv}"
+            );
+
             last_pv = pv;
             last_score = score;
+            if M::MAIN_THREAD {
+                self.set_current_best(last_pv[0], score, i);
+            }
             // terminate search if we are hinted to do so or the next depth would likely take too long
             if let Some((stop_hint, abort_time)) = self.max_time_ms {
                 if (end - start).as_millis() as usize >= stop_hint {
@@ -380,7 +708,7 @@ impl Search {
             }
 
             // terminate search at max nodes
-            if let Some(max_nodes) = self.max_nodes {
+            if let Some(max_nodes) = self.max_nodes.into_iter().chain(self.soft_max_nodes).min() {
                 if nodes >= max_nodes {
                     if set_global_abort {
                         ABORT_SEARCH.store(true, Relaxed);
@@ -404,7 +732,39 @@ impl Search {
                 break;
             }
         }
-        (last_score, last_pv, self.tablebases)
+        (last_score, last_pv, self.tablebases.take())
+    }
+
+    // score every root move with a quiescence search alone, so there is still a best move to
+    // report. mates and stalemates at the root fall through to a plain quiescence search
+    fn quiesce_root<M: TypeMainThread>(
+        &mut self,
+        tt: &TranspositionTable,
+    ) -> (i16, PrincipalVariation) {
+        let board = self.game;
+        let mut best_score = MINUS_INF;
+        let mut pv = PrincipalVariation::new().chess_960(self.chess_960);
+        let mut line = PrincipalVariation::new();
+
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            let score = self.quiesce::<M>(&board, MINUS_INF, INF, 0, &mut line, tt);
+            return (score, pv);
+        }
+
+        for smv in &moves {
+            let mut new = board;
+            new.make_move(smv.mv);
+            let score = -self.quiesce::<M>(&new, -INF, -best_score, 1, &mut line, tt);
+            if ABORT_SEARCH.load(Relaxed) && !pv.is_empty() {
+                break;
+            }
+            if score > best_score {
+                best_score = score;
+                pv.update_from(smv.mv, &line);
+            }
+        }
+        (best_score, pv)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -443,12 +803,26 @@ impl Search {
         // before we actually use it
         tt.prefetch(board.hash());
 
-        // Check extensions: increase depth by 1 when in check to avoid tactical blindness
+        // Check extensions: increase depth by 1 when in check to avoid tactical blindness.
+        // optionally not for spite checks that hang the checking piece, and only so many times
+        // per line, so long series of checks can't blow up the tree
         let in_check = board.in_check();
-        if in_check {
+        let (parent_extensions, losing_check) = match ply.checked_sub(1) {
+            Some(parent) => {
+                let parent = &self.thread_data.search_stack[parent];
+                (parent.check_extensions, parent.losing_check)
+            }
+            None => (0, false),
+        };
+        let extend = in_check
+            && (!self.options.limit_check_extensions
+                || (!losing_check && parent_extensions < MAX_CHECK_EXTENSIONS));
+        if extend {
             // saturating add to avoid negative depths on overflow
             depth = depth.saturating_add(1);
         }
+        self.thread_data.search_stack[ply].check_extensions =
+            parent_extensions.saturating_add(extend as u8);
 
         // the PV from this node will be gathered into this array
         let mut line = PrincipalVariation::new();
@@ -465,7 +839,7 @@ impl Search {
         if M::MAIN_THREAD {
             let old_nodes = NODE_COUNT.fetch_add(1, Relaxed);
             // if this is the last node, allow it to complete, otherwise subtract this node from the count
-            if self.max_nodes.is_some_and(|n| old_nodes >= n) {
+            if self.node_limit_reached(old_nodes) {
                 NODE_COUNT.fetch_sub(1, Relaxed);
                 ABORT_SEARCH.store(true, Relaxed);
                 pv.clear();
@@ -479,23 +853,11 @@ impl Search {
         let pv_node = alpha != beta - 1;
         let current_player = board.current_player();
 
-        // check 50 move and repetition draws when not at the root
+        // check 50 move, repetition and dead position draws when not at the root
         if !R::ROOT
             && (board.halfmove_clock() >= 100
-                || self
-                    .pre_history
-                    .iter()
-                    .rev()
-                    .take(board.halfmove_clock() as usize)
-                    .filter(|&&h| h == board.hash())
-                    .count()
-                    >= 2
-                || self
-                    .search_history
-                    .iter()
-                    .rev()
-                    .take(board.halfmove_clock() as usize)
-                    .any(|h| *h == board.hash()))
+                || board.is_dead_position()
+                || self.is_repetition(board))
         {
             pv.clear();
             // randomise around the draw score slightly to improve searching of draws
@@ -503,14 +865,19 @@ impl Search {
         }
 
         // Mate distance pruning: we can never find a score better than mate at the current ply
-        // or worse than being mated at the current ply
+        // or worse than being mated at the current ply. stay fail-soft when this empties the
+        // window by returning the mate bound itself, which is tighter than alpha or beta
         if !R::ROOT {
-            alpha = alpha.max(-CHECKMATE_SCORE + ply as i16);
-            beta = beta.min(CHECKMATE_SCORE - ply as i16);
-
-            if alpha >= beta {
-                return beta;
+            let mated_score = -CHECKMATE_SCORE + ply as i16;
+            let mating_score = CHECKMATE_SCORE - ply as i16;
+            if mated_score >= beta {
+                return mated_score;
             }
+            if mating_score <= alpha {
+                return mating_score;
+            }
+            alpha = alpha.max(mated_score);
+            beta = beta.min(mating_score);
         }
 
         let mut tt_move = Move::null();
@@ -539,6 +906,44 @@ impl Search {
             tt_depth = entry.depth;
         }
 
+        // TT move cutoff: at shallow depth, trust a lower bound above beta from a slightly
+        // shallower search once a reduced search of the TT move alone confirms it. Mate and TB
+        // scores are left to the full search
+        if !R::ROOT
+            && !pv_node
+            && !in_check
+            && depth <= self.options.tt_move_cutoff_depth
+            && tt_bound == LowerBound
+            && tt_depth >= depth - 2
+            && tt_score >= beta
+            && tt_score < TB_WIN_SCORE - SEARCH_MAX_PLY as i16
+            && board.is_pseudolegal(tt_move)
+        {
+            let mut new = *board;
+            new.make_move(tt_move);
+            if !new.illegal_position() {
+                self.search_history.push(board.hash());
+                self.thread_data.search_stack[ply].current_move = tt_move;
+                self.thread_data.search_stack[ply].losing_check = false;
+                let score = -self.negamax::<NotRoot, M>(
+                    &new,
+                    -beta,
+                    -beta + 1,
+                    (depth - 2).max(0),
+                    ply + 1,
+                    &mut line,
+                    tt,
+                    true,
+                );
+                self.search_history.pop();
+
+                if score >= beta {
+                    pv.clear();
+                    return score;
+                }
+            }
+        }
+
         // Probe the Syzygy tablebases if they are available
         let (mut tb_max, mut tb_min) = (INF, MINUS_INF);
         if !R::ROOT
@@ -673,6 +1078,24 @@ impl Search {
                 );
                 self.search_history.pop();
 
+                // verify deep cutoffs with a reduced search that can't null move either, so
+                // zugzwang positions where passing is the best option don't get pruned
+                if score >= beta
+                    && self.options.nmp_verification_depth != 0
+                    && depth >= self.options.nmp_verification_depth
+                {
+                    score = self.negamax::<NotRoot, M>(
+                        board,
+                        beta - 1,
+                        beta,
+                        (depth - reduction).max(0),
+                        ply,
+                        &mut PrincipalVariation::new(),
+                        tt,
+                        false,
+                    );
+                }
+
                 if score >= beta {
                     // don't let TB results leak out of NMP
                     if score >= TB_WIN_SCORE - SEARCH_MAX_PLY as i16 {
@@ -771,6 +1194,20 @@ impl Search {
                 // skip the TT move if it's illegal
                 continue;
             }
+            self.thread_data.search_stack[ply].losing_check = self.options.limit_check_extensions
+                && new.in_check()
+                && !board.see_beats_threshold(mv, 0);
+
+            // opening diversity: let near-equal root moves trade places. each root move is searched
+            // against a window shifted by its noise, so the PVS decisions below agree with the
+            // noisy score that ends up compared against alpha and beta
+            let noise = if R::ROOT && self.options.eval_noise > 0 {
+                self.root_noise(mv)
+            } else {
+                0
+            };
+            let search_alpha = alpha.saturating_sub(noise).max(-INF);
+            let search_beta = beta.saturating_sub(noise).max(-INF);
 
             let mut score = MINUS_INF;
             // perform a search on the new position, returning the score and the PV
@@ -787,7 +1224,7 @@ impl Search {
                         && !(COUNTERMOVE_SCORE..KILLER_MOVE_SCORE + 50_000).contains(&move_score)
                         && mv.promotion() != Queen
                     {
-                        r += LMR[(depth as usize).min(63)][move_index.min(63)];
+                        r += self.lmr[(depth as usize).min(63)][move_index.min(63)];
 
                         // reduce more outside of PV
                         r += !pv_node as i8;
@@ -797,6 +1234,12 @@ impl Search {
                         r -= (histories / self.options.history_lmr_divisor) as i8;
                         // don't allow negative reductions
                         r = r.max(0);
+                    } else if capture
+                        && self.options.capture_history_lmr_divisor != 0
+                        && !(COUNTERMOVE_SCORE..KILLER_MOVE_SCORE + 50_000).contains(&move_score)
+                        && mv.promotion() != Queen
+                    {
+                        r += self.capture_reduction(mv, current_player, depth, move_index, pv_node);
                     }
 
                     r
@@ -806,8 +1249,8 @@ impl Search {
                 let reduced_depth = (depth - 1 - reduction).max(0);
                 score = -self.negamax::<NotRoot, M>(
                     &new,
-                    -alpha - 1,
-                    -alpha,
+                    -search_alpha - 1,
+                    -search_alpha,
                     reduced_depth,
                     ply + 1,
                     &mut line,
@@ -816,7 +1259,7 @@ impl Search {
                 );
 
                 // perform a full-depth null-window search if the reduced search improves alpha and the move was actually reduced
-                score > alpha && reduction > 0
+                score > search_alpha && reduction > 0
             } else {
                 // if the first condition fails, perform the full depth null window search in non-pv nodes or later moves in PVS
                 !pv_node || move_index > 0
@@ -827,8 +1270,8 @@ impl Search {
             if full_depth_null_window {
                 score = -self.negamax::<NotRoot, M>(
                     &new,
-                    -alpha - 1,
-                    -alpha,
+                    -search_alpha - 1,
+                    -search_alpha,
                     depth - 1,
                     ply + 1,
                     &mut line,
@@ -838,11 +1281,11 @@ impl Search {
             }
 
             // perform a full-depth full-window search in PV nodes on the first move and reduced moves that improve alpha
-            if pv_node && (move_index == 0 || (score > alpha && score < beta)) {
+            if pv_node && (move_index == 0 || (score > search_alpha && score < search_beta)) {
                 score = -self.negamax::<NotRoot, M>(
                     &new,
-                    -beta,
-                    -alpha,
+                    -search_beta,
+                    -search_alpha,
                     depth - 1,
                     ply + 1,
                     &mut line,
@@ -856,6 +1299,12 @@ impl Search {
                 self.root_nodes[mv.from()][mv.to()] += self.local_nodes - old_nodes;
             }
 
+            // mate and TB scores are left alone so the noise can't turn a win into something else
+            let raw_score = score;
+            if noise != 0 && score.abs() < TB_WIN_SCORE - SEARCH_MAX_PLY as i16 {
+                score += noise;
+            }
+
             // scores can't be trusted after an abort, don't let them get into the TT
             if ABORT_SEARCH.load(Relaxed) && depth > 1 {
                 // remove this position from the history
@@ -868,12 +1317,12 @@ impl Search {
                 // beta cutoff, this move is too good and so the opponent won't go into this position
                 pv.clear();
 
-                // add the score and move to TT
+                // add the score and move to TT, without the noise
                 tt.set(
                     board.hash(),
                     mv,
                     depth,
-                    score_into_tt(score, ply),
+                    score_into_tt(raw_score, ply),
                     LowerBound,
                     pv_node,
                 );
@@ -959,17 +1408,28 @@ impl Search {
 
         // after all moves have been searched, alpha is either unchanged
         // (this position is bad) or raised (new pv from this node)
+        let (tt_score, bound) = if R::ROOT
+            && self.options.eval_noise > 0
+            && best_score.abs() < TB_WIN_SCORE - SEARCH_MAX_PLY as i16
+        {
+            // a noisy root score is only known to be within eval_noise of the real one
+            if best_score > old_alpha {
+                (best_score - self.options.eval_noise, LowerBound)
+            } else {
+                (best_score + self.options.eval_noise, UpperBound)
+            }
+        } else if best_score > old_alpha {
+            (best_score, Exact)
+        } else {
+            (best_score, UpperBound)
+        };
         // add the score and the new best move to the TT
         tt.set(
             board.hash(),
             best_move,
             depth,
-            score_into_tt(best_score, ply),
-            if best_score > old_alpha {
-                Exact
-            } else {
-                UpperBound
-            },
+            score_into_tt(tt_score, ply),
+            bound,
             pv_node,
         );
 
@@ -1018,7 +1478,7 @@ impl Search {
         if M::MAIN_THREAD {
             let old_nodes = NODE_COUNT.fetch_add(1, Relaxed);
             // if this is the last node, allow it to complete, otherwise subtract this node from the count
-            if self.max_nodes.is_some_and(|n| old_nodes >= n) {
+            if self.node_limit_reached(old_nodes) {
                 NODE_COUNT.fetch_sub(1, Relaxed);
                 ABORT_SEARCH.store(true, Relaxed);
                 pv.clear();
@@ -1098,6 +1558,7 @@ impl Search {
                 )
                 .saturating_add(self.options.delta_pruning_margin)
                 <= alpha
+                && !(self.options.delta_pruning_keeps_checks && board.gives_check(mv))
             {
                 continue;
             }
@@ -1158,17 +1619,12 @@ impl Search {
         self.search_history.pop();
 
         // if there are no legal captures, check for checkmate/stalemate
-        if self.thread_data.search_stack[ply].num_moves() == 0 {
-            let mut some_moves = false;
-            board.generate_legal_moves(|mvs| some_moves = some_moves || mvs.moves.is_not_empty());
-
-            if !some_moves {
-                pv.clear();
-                if board.in_check() {
-                    return -(CHECKMATE_SCORE - (ply as i16));
-                } else {
-                    return DRAW_SCORE;
-                }
+        if self.thread_data.search_stack[ply].num_moves() == 0 && !board.has_legal_move() {
+            pv.clear();
+            if board.in_check() {
+                return -(CHECKMATE_SCORE - (ply as i16));
+            } else {
+                return DRAW_SCORE;
             }
         }
 
@@ -1194,3 +1650,1058 @@ impl Search {
         best_score
     }
 }
+
+// more search threads than hardware threads only adds contention on the TT, so cap the
+// thread count unless oversubscription was explicitly asked for
+pub fn effective_threads(
+    requested: usize,
+    available: usize,
+    allow_oversubscription: bool,
+) -> usize {
+    if allow_oversubscription {
+        requested.max(1)
+    } else {
+        requested.clamp(1, available.max(1))
+    }
+}
+
+// LMR reductions for base + ln(depth) * ln(move index) / divisor, in hundredths. the defaults
+// keep the pregenerated table, which has the tuned coefficients at full precision
+fn lmr_table(base: i16, divisor: i16) -> [[i8; 64]; 64] {
+    if base == LMR_BASE && divisor == LMR_DIVISOR {
+        LMR
+    } else {
+        lmr_formula(base, divisor)
+    }
+}
+
+fn lmr_formula(base: i16, divisor: i16) -> [[i8; 64]; 64] {
+    let base = base as f32 / 100.0;
+    let divisor = divisor.max(1) as f32 / 100.0;
+    let mut table = [[0; 64]; 64];
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (played, r) in row.iter_mut().enumerate().skip(1) {
+            *r = (base + (depth as f32).ln() * (played as f32).ln() / divisor) as i8;
+        }
+    }
+    table
+}
+
+// coefficients of cubic polynomials in the game ply / 64 for the centre and spread of the
+// logistic win rate curve, fitted by maximum likelihood to the results of about 157k positions
+// from 1069 self-play games at 20000 nodes per move, started from 8 ply `genfens` openings
+const WDL_AS: [f64; 4] = [7.53361222, 21.69514485, -2.60820162, 53.94260089];
+const WDL_BS: [f64; 4] = [-1.25569690, 30.66688022, -39.57685578, 69.31378727];
+
+// which iterations a Lazy SMP helper skips: helpers are split into groups skipping blocks of
+// 1 to 4 depths, each helper in a group offset from the others. the main thread searches every
+// depth, and depth 1 is never skipped so every thread has a score for its aspiration windows
+const SKIP_SIZE: [usize; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+fn skip_depth(thread_index: usize, depth: usize) -> bool {
+    if thread_index == 0 || depth == 1 {
+        return false;
+    }
+    let i = (thread_index - 1) % SKIP_SIZE.len();
+    !((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]).is_multiple_of(2)
+}
+
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+// win, draw and loss in permille for the side to move, given a search score and the game ply.
+// `Search` only knows the moves since the position command, so passes `pre_history.len()`
+pub fn score_to_wdl(score: i16, ply: usize) -> (u16, u16, u16) {
+    if CHECKMATE_SCORE - score.abs() < SEARCH_MAX_PLY as i16 {
+        return if score > 0 {
+            (1000, 0, 0)
+        } else {
+            (0, 0, 1000)
+        };
+    }
+
+    let m = ply.min(240) as f64 / 64.0;
+    let a = WDL_AS.iter().fold(0.0, |acc, c| acc * m + c);
+    let b = WDL_BS.iter().fold(0.0, |acc, c| acc * m + c);
+    let x = f64::from(score).clamp(-4000.0, 4000.0);
+
+    let win = (1000.0 / (1.0 + ((a - x) / b).exp())).round() as u16;
+    let loss = (1000.0 / (1.0 + ((a + x) / b).exp())).round() as u16;
+    (win, 1000 - win - loss, loss)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::Ordering, mpsc, Arc, Mutex, RwLock},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use cheers_bitboards::Square;
+    use cheers_pregen::LMR;
+    use pyrrhic_rs::TableBases;
+
+    use crate::{
+        affinity::ThreadPinner,
+        board::{tb_adapter::MovegenAdapter, Board},
+        hash_tables::{NodeType, TranspositionTable},
+        history_tables::HISTORY_MAX,
+        moves::{Move, PrincipalVariation, PV_MAX_LEN},
+        options::{SearchOptions, HISTORY_LMR_DIVISOR, LMR_BASE, LMR_DIVISOR},
+        types::{Color, MainThread, Piece::*, Root},
+    };
+
+    use super::{
+        effective_threads, lmr_formula, lmr_table, score_to_wdl, skip_depth, Search, ABORT_SEARCH,
+        ASPIRATION_RESEARCHES, CHECKMATE_SCORE, INF, MINUS_INF, NODE_COUNT, SEARCH_MAX_PLY,
+        TB_HITS,
+    };
+
+    // searches share global abort and node counters, so they can't run in parallel
+    static SEARCH_LOCK: Mutex<()> = Mutex::new(());
+
+    // unoptimised search frames are large, so give the search a bigger stack than the test harness
+    fn with_big_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+        thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(f)
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn run_search(search: impl FnOnce() -> Search + Send + 'static) -> (i16, PrincipalVariation) {
+        with_big_stack(move || {
+            let (score, pv, _) = search().smp_search();
+            (score, pv)
+        })
+    }
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    // a fresh search of the position with these options, returning its score, legal PV and
+    // node count
+    fn search_with(
+        fen: &'static str,
+        options: SearchOptions,
+        depth: usize,
+    ) -> (i16, PrincipalVariation, usize) {
+        let (score, pv) = run_search(move || {
+            Search::new(Board::from_fen(fen).unwrap())
+                .tt_size_mb(1)
+                .options(options)
+                .max_depth(Some(depth))
+        });
+        assert!(
+            Board::from_fen(fen).unwrap().is_legal_line(pv.moves()),
+            "Illegal PV for {fen}: {pv}"
+        );
+        (score, pv, NODE_COUNT.load(Ordering::Relaxed))
+    }
+
+    fn nodes_with(options: SearchOptions, depth: usize) -> usize {
+        search_with(KIWIPETE, options, depth).2
+    }
+
+    #[test]
+    fn test_pv_length_and_legality() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let (_, pv) = run_search(move || Search::new(board).tt_size_mb(1).max_depth(Some(6)));
+
+            assert!(!pv.is_empty(), "Empty PV for {fen}");
+            assert!(pv.len() <= PV_MAX_LEN, "PV too long for {fen}");
+
+            assert!(
+                board.is_legal_line(pv.moves()),
+                "Illegal PV for {fen}: {pv}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_pv_legal_after_tt_reuse() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let tt = Arc::new(RwLock::new(TranspositionTable::new(1)));
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            // search each position twice so the second search is full of TT cutoffs
+            for depth in [5, 7] {
+                let tt = tt.clone();
+                let (_, pv) =
+                    run_search(move || Search::new_with_tt(board, tt).max_depth(Some(depth)));
+                assert!(
+                    board.is_legal_line(pv.moves()),
+                    "Illegal PV for {fen}: {pv}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_movetime_zero() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+
+        let start = Instant::now();
+        let (_, pv) = run_search(move || {
+            let mut search = Search::new(board).tt_size_mb(1);
+            search.max_time_ms = Some((0, 0));
+            search
+        });
+
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "movetime 0 took too long"
+        );
+        assert!(!pv.is_empty(), "No bestmove with movetime 0");
+        assert!(board.is_legal_line(pv.moves()), "Illegal PV: {pv}");
+    }
+
+    #[test]
+    fn test_current_best() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+
+        // searches are too big for the test stack, so poll a boxed clone
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || {
+                let mut search = Search::new(board).tt_size_mb(1);
+                sender.send(Box::new(search.clone())).unwrap();
+                search.smp_search()
+            })
+            .unwrap();
+        let poll = receiver.recv().unwrap();
+
+        // the free queen should be found early and stay the best move
+        let (best, _, depth) = loop {
+            match poll.current_best() {
+                Some(best) if best.2 >= 6 => break best,
+                _ => thread::sleep(Duration::from_millis(1)),
+            }
+        };
+        assert_eq!(best.coords(), "d1d5", "Wrong best move at depth {depth}");
+
+        ABORT_SEARCH.store(true, Ordering::Relaxed);
+        let (_, pv, _) = handle.join().unwrap();
+        assert_eq!(pv[0], best);
+        assert_eq!(poll.current_best().map(|(mv, _, _)| mv), Some(best));
+    }
+
+    #[test]
+    fn test_dead_position_draw() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // same coloured bishops can never mate, whatever the material imbalance
+        let board = Board::from_fen("4k3/8/8/8/8/2b5/8/B1B1K1B1 w - - 0 1").unwrap();
+        let (score, _) = run_search(move || Search::new(board).tt_size_mb(1).max_depth(Some(4)));
+        assert!(score.abs() <= 4, "Dead position scored {score}");
+    }
+
+    #[test]
+    fn test_depth_zero() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // the queen is hanging, which the quiescence search alone sees
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+
+        let (score, pv) = run_search(move || Search::new(board).tt_size_mb(1).max_depth(Some(0)));
+
+        assert!(board.is_legal_line(pv.moves()), "Illegal PV: {pv}");
+        assert_eq!(pv[0], Move::new(Rook, Square::D1, Square::D5, Pawn));
+        assert!(score > 0);
+
+        // quiet positions still get a move, from one quiescence search per root move
+        let (score, pv) = run_search(|| Search::new(Board::new()).tt_size_mb(1).max_depth(Some(0)));
+        let nodes = NODE_COUNT.load(Ordering::Relaxed);
+        assert!(nodes <= 2 * Board::new().legal_moves().len(), "{nodes}");
+        assert!(Board::new().is_legal_line(pv.moves()) && !pv.is_empty());
+        assert!(score.abs() < 200);
+    }
+
+    #[test]
+    fn test_single_legal_move() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // the king is in check with only one escape square
+        let board = Board::from_fen("R6k/8/5K2/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.legal_moves().len(), 1);
+
+        let start = Instant::now();
+        let (_, pv) = run_search(move || {
+            let mut search = Search::new(board).tt_size_mb(1);
+            search.max_time_ms = Some((10_000, 10_000));
+            search
+        });
+
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "Single reply took too long"
+        );
+        assert_eq!(pv[0].coords(), "h8h7");
+    }
+
+    #[test]
+    fn test_effective_threads() {
+        assert_eq!(effective_threads(256, 4, false), 4);
+        assert_eq!(effective_threads(256, 4, true), 256);
+        assert_eq!(effective_threads(2, 4, false), 2);
+        assert_eq!(effective_threads(0, 4, false), 1);
+
+        // never more than asked for, nor more than the hardware has unless allowed
+        for requested in 1..=256 {
+            for available in 1..=64 {
+                let clamped = effective_threads(requested, available, false);
+                assert!(clamped <= requested && clamped <= available);
+                assert_eq!(effective_threads(requested, available, true), requested);
+            }
+        }
+    }
+
+    #[test]
+    fn test_score_to_wdl() {
+        // an equal middlegame is most likely drawn, and more so as the game goes on
+        let (win, draw, loss) = score_to_wdl(0, 60);
+        assert_eq!(win, loss);
+        assert!(draw > win + loss, "{win} {draw} {loss}");
+        assert!(score_to_wdl(0, 150).1 > draw);
+
+        let (win, draw, loss) = score_to_wdl(300, 60);
+        assert_eq!(win + draw + loss, 1000);
+        assert!(win > 500 && loss < 10, "{win} {draw} {loss}");
+
+        assert_eq!(score_to_wdl(CHECKMATE_SCORE - 5, 60), (1000, 0, 0));
+        assert_eq!(score_to_wdl(-CHECKMATE_SCORE + 6, 60), (0, 0, 1000));
+    }
+
+    #[derive(Default)]
+    struct MockPinner {
+        cores: Mutex<Vec<usize>>,
+    }
+
+    impl ThreadPinner for MockPinner {
+        fn pin_current_thread(&self, core: usize) -> bool {
+            self.cores.lock().unwrap().push(core);
+            true
+        }
+    }
+
+    #[test]
+    fn test_thread_affinity() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for thread_affinity in [false, true] {
+            let pinned = thread::Builder::new()
+                .stack_size(64 * 1024 * 1024)
+                .spawn(move || {
+                    let pinner = MockPinner::default();
+                    let options = SearchOptions {
+                        threads: 3,
+                        allow_oversubscription: true,
+                        thread_affinity,
+                        ..Default::default()
+                    };
+                    Search::new(Board::new())
+                        .tt_size_mb(1)
+                        .options(options)
+                        .max_depth(Some(2))
+                        .smp_search_with_pinner(&pinner);
+
+                    let mut cores = pinner.cores.into_inner().unwrap();
+                    cores.sort();
+                    cores
+                })
+                .unwrap()
+                .join()
+                .unwrap();
+
+            if thread_affinity {
+                assert_eq!(pinned, vec![0, 1, 2]);
+            } else {
+                assert!(pinned.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tb_hits() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // the count is reset for each search
+        TB_HITS.store(5, Ordering::Relaxed);
+        run_search(|| Search::new(Board::new()).tt_size_mb(1).max_depth(Some(2)));
+        assert_eq!(TB_HITS.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    #[ignore = "needs Syzygy tables, run with SYZYGY_PATH set"]
+    fn test_tb_hits_with_tablebases() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH is not set");
+        let hits = |threads| {
+            let path = path.clone();
+            run_search(move || {
+                let tablebases = TableBases::<MovegenAdapter>::new(path).unwrap();
+                let options = SearchOptions {
+                    threads,
+                    allow_oversubscription: true,
+                    ..Default::default()
+                };
+                // too many pieces for a root probe with up to 6-man tables, so the search
+                // probes after captures instead
+                let board = Board::from_fen("8/5p2/3k4/8/2r5/3P4/3R1P2/3K4 w - - 0 1").unwrap();
+                Search::new(board)
+                    .tt_size_mb(1)
+                    .tablebases(Some(tablebases))
+                    .options(options)
+                    .max_depth(Some(8))
+            });
+            TB_HITS.load(Ordering::Relaxed)
+        };
+        assert!(hits(1) > 0);
+        // helper threads add to the shared count too, but how much depends on their timing
+        assert!(hits(4) > 0);
+    }
+
+    #[test]
+    fn test_tt_move_cutoff() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let search = |fen: &'static str, tt_move_cutoff_depth| {
+            let (score, pv) = run_search(move || {
+                let options = SearchOptions {
+                    tt_move_cutoff_depth,
+                    ..Default::default()
+                };
+                Search::new(Board::from_fen(fen).unwrap())
+                    .tt_size_mb(1)
+                    .options(options)
+                    .max_depth(Some(9))
+            });
+            (score, pv[0], NODE_COUNT.load(Ordering::Relaxed))
+        };
+
+        let (mut nodes_off, mut nodes_on) = (0, 0);
+        for (fen, best_move) in [
+            ("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", Some("d1d5")),
+            ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", Some("d1d8")),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                None,
+            ),
+            (
+                "rnbqkb1r/pp1p1ppp/5n2/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 4",
+                None,
+            ),
+        ] {
+            let (score_off, move_off, nodes) = search(fen, 0);
+            nodes_off += nodes;
+            let (score_on, move_on, nodes) = search(fen, 3);
+            nodes_on += nodes;
+
+            if let Some(best_move) = best_move {
+                assert_eq!(move_off.coords(), best_move, "{fen}");
+                assert_eq!(move_on.coords(), best_move, "{fen}");
+            }
+            assert!(
+                score_off.abs_diff(score_on) <= 50,
+                "{fen}: {score_off} without the cutoff, {score_on} with it"
+            );
+        }
+        // the cutoff must actually change the tree, and not blow it up
+        assert_ne!(nodes_on, nodes_off);
+        assert!(
+            nodes_on < nodes_off * 3 / 2,
+            "{nodes_on} nodes with the TT move cutoff, {nodes_off} without"
+        );
+    }
+
+    #[test]
+    fn test_node_limit_reproducible() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let search = || {
+            let (_, pv) = run_search(|| {
+                Search::new(Board::new())
+                    .tt_size_mb(1)
+                    .max_nodes(Some(100000))
+            });
+            (pv[0], NODE_COUNT.load(Ordering::Relaxed))
+        };
+
+        let (best_move, nodes) = search();
+        assert!(nodes <= 100000, "{nodes} nodes");
+        for _ in 0..2 {
+            assert_eq!(search(), (best_move, nodes));
+        }
+
+        // the soft limit lets the iteration that crosses it finish
+        let (_, pv) = run_search(|| {
+            Search::new(Board::new())
+                .tt_size_mb(1)
+                .soft_max_nodes(Some(100000))
+        });
+        assert!(!pv.is_empty());
+        assert!(NODE_COUNT.load(Ordering::Relaxed) >= 100000);
+    }
+
+    #[test]
+    fn test_node_limit_without_global_abort() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // a single search that doesn't own the abort flag, with both a node and a depth limit:
+        // whichever comes first stops it
+        let search = |nodes, depth| {
+            with_big_stack(move || {
+                let mut search = Search::new(Board::new())
+                    .tt_size_mb(1)
+                    .max_nodes(Some(nodes))
+                    .max_depth(Some(depth))
+                    .output(false);
+                let (_, pv, _) = search.search::<MainThread>(false);
+                (pv, NODE_COUNT.load(Ordering::Relaxed))
+            })
+        };
+
+        let (pv, nodes) = search(5000, 64);
+        assert!(!pv.is_empty());
+        assert!(nodes <= 5000, "{nodes} nodes");
+
+        let (pv, nodes) = search(10_000_000, 4);
+        assert!(!pv.is_empty());
+        assert!(nodes < 100_000, "{nodes} nodes");
+    }
+
+    #[test]
+    fn test_repetition_history() {
+        let play = |board: &mut Board, history: &mut Vec<u64>, moves: &[&str]| {
+            for mv in moves {
+                history.push(board.hash());
+                board.make_move(Move::from_pair(board, mv));
+            }
+        };
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        // the start position occurred twice before the root, its third occurrence is the root's
+        // child after Ng8
+        let mut root = Board::new();
+        let mut history = Vec::new();
+        play(&mut root, &mut history, &shuffle);
+        play(&mut root, &mut history, &shuffle[..3]);
+        let mut child = root;
+        child.make_move(Move::from_pair(&root, "f6g8"));
+
+        for root_in_search_history in [true, false] {
+            let history = history.clone();
+            assert!(with_big_stack(move || {
+                let mut search = Search::new(root)
+                    .pre_history(history)
+                    .root_in_search_history(root_in_search_history);
+                search.search_history.push(root.hash());
+                search.is_repetition(&child)
+            }));
+        }
+
+        // from a fresh start position, one return to the root only draws while the root is part
+        // of the search history
+        let mut grandchild = Board::new();
+        play(&mut grandchild, &mut Vec::new(), &shuffle);
+        for root_in_search_history in [true, false] {
+            let repetition = with_big_stack(move || {
+                let mut search =
+                    Search::new(Board::new()).root_in_search_history(root_in_search_history);
+                search.search_history.push(Board::new().hash());
+                search.is_repetition(&grandchild)
+            });
+            assert_eq!(repetition, root_in_search_history);
+        }
+    }
+
+    #[test]
+    fn test_volatile_aspiration() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // the score drops by several pawns between the first iterations, so a one centipawn
+        // window has to widen a long way without searching forever
+        let search = |use_aspiration| {
+            let options = SearchOptions {
+                use_aspiration,
+                aspiration_delta: 1,
+                ..Default::default()
+            };
+            let (score, pv, _) = search_with("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", options, 8);
+            (score, pv[0].coords())
+        };
+
+        let (full_score, full_move) = search(false);
+        let (score, best_move) = search(true);
+        let researches = ASPIRATION_RESEARCHES.load(Ordering::Relaxed);
+
+        assert!((1..1000).contains(&researches), "{researches} re-searches");
+        assert_eq!(best_move, full_move);
+        assert!(
+            score.abs_diff(full_score) <= 30,
+            "{score} with aspiration windows, {full_score} without"
+        );
+    }
+
+    #[test]
+    fn test_mate_bounds() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // mate in 2 with the rooks
+        let fen = "7k/8/8/8/8/8/8/RR4K1 w - - 0 1";
+        let mate_score = CHECKMATE_SCORE - 3;
+
+        let (score, _) = run_search(move || {
+            let options = SearchOptions {
+                aspiration_delta: 1,
+                ..Default::default()
+            };
+            Search::new(Board::from_fen(fen).unwrap())
+                .tt_size_mb(1)
+                .options(options)
+                .max_depth(Some(6))
+        });
+        assert_eq!(score, mate_score);
+
+        // windows with the mate score just inside, and at either edge, must still return it.
+        // shallower full window searches first fill the TT for move ordering
+        let window_score = |alpha: i16, beta: i16| {
+            with_big_stack(move || {
+                ABORT_SEARCH.store(false, Ordering::Relaxed);
+                let board = Board::from_fen(fen).unwrap();
+                let tt = TranspositionTable::new(1);
+                let mut search = Search::new(board);
+                let mut pv = PrincipalVariation::new();
+                for depth in 1..6 {
+                    search.negamax::<Root, MainThread>(
+                        &board, MINUS_INF, INF, depth, 0, &mut pv, &tt, true,
+                    );
+                }
+                search.negamax::<Root, MainThread>(&board, alpha, beta, 6, 0, &mut pv, &tt, true)
+            })
+        };
+        assert_eq!(window_score(mate_score - 1, mate_score + 1), mate_score);
+        assert_eq!(window_score(mate_score - 10, mate_score), mate_score);
+        assert_eq!(window_score(mate_score, mate_score + 10), mate_score);
+    }
+
+    #[test]
+    fn test_long_halfmove_clock() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // far past the 50 move rule, and too long for a u8. nothing can reset the clock
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 300 1";
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.halfmove_clock(), 300);
+        assert_eq!(board.fen(), fen);
+
+        let (score, pv) = run_search(move || Search::new(board).tt_size_mb(1).max_depth(Some(4)));
+        assert!(!pv.is_empty());
+        assert!(score.abs() <= 8, "{score}");
+    }
+
+    #[test]
+    fn test_winning_side_avoids_repetition() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // KQvK: play the game out, both sides searching with the game history so far
+        let mut board = Board::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut history = Vec::new();
+        for _ in 0..100 {
+            if board.legal_moves().is_empty() {
+                break;
+            }
+            let (game, pre_history) = (board, history.clone());
+            let (_, pv) = run_search(move || {
+                Search::new(game)
+                    .tt_size_mb(1)
+                    .pre_history(pre_history)
+                    .max_depth(Some(10))
+            });
+            history.push(board.hash());
+            board.make_move(pv[0]);
+
+            let repeats = history.iter().filter(|&&h| h == board.hash()).count();
+            assert!(repeats < 2, "threefold repetition in {}", board.fen());
+        }
+        assert!(
+            board.in_check() && board.legal_moves().is_empty(),
+            "no mate: {}",
+            board.fen()
+        );
+    }
+
+    #[test]
+    fn test_disable_aspiration() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let researches = |use_aspiration| {
+            let options = SearchOptions {
+                use_aspiration,
+                ..Default::default()
+            };
+            nodes_with(options, 8);
+            ASPIRATION_RESEARCHES.load(Ordering::Relaxed)
+        };
+
+        assert!(researches(true) > 0);
+        assert_eq!(researches(false), 0);
+    }
+
+    #[test]
+    fn test_eval_noise() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let best_moves = |eval_noise| {
+            (0..12)
+                .map(|seed| {
+                    let (_, pv) = run_search(move || {
+                        let options = SearchOptions {
+                            eval_noise,
+                            ..Default::default()
+                        };
+                        Search::new(Board::new())
+                            .tt_size_mb(1)
+                            .options(options)
+                            .noise_seed(seed)
+                            .max_depth(Some(5))
+                    });
+                    pv[0]
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let quiet = best_moves(0);
+        assert!(quiet.iter().all(|&mv| mv == quiet[0]));
+        let noisy = best_moves(50);
+        assert!(noisy.iter().any(|&mv| mv != noisy[0]));
+    }
+
+    #[test]
+    fn test_eval_noise_kept_out_of_tt() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let tt = Arc::new(RwLock::new(TranspositionTable::new(1)));
+        let search_tt = tt.clone();
+        run_search(move || {
+            let options = SearchOptions {
+                eval_noise: 50,
+                ..Default::default()
+            };
+            Search::new_with_tt(Board::new(), search_tt)
+                .options(options)
+                .noise_seed(3)
+                .max_depth(Some(5))
+        });
+        // the root score has noise in it, so it can only be stored as a bound
+        let entry = tt.read().unwrap().get(Board::new().hash()).unwrap();
+        assert!(entry.node_type != NodeType::Exact);
+    }
+
+    #[test]
+    fn test_helper_depth_skipping() {
+        let searched = |thread_index| {
+            (1..=12)
+                .filter(|&depth| !skip_depth(thread_index, depth))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(searched(0), (1..=12).collect::<Vec<_>>());
+        assert_eq!(searched(1), [1, 2, 4, 6, 8, 10, 12]);
+        assert_eq!(searched(2), [1, 3, 5, 7, 9, 11]);
+        // no two of the first helpers search the same depths
+        for a in 1..=6 {
+            for b in a + 1..=6 {
+                assert_ne!(searched(a), searched(b));
+            }
+        }
+
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let run = |fen, threads| {
+            let options = SearchOptions {
+                threads,
+                allow_oversubscription: true,
+                ..Default::default()
+            };
+            search_with(fen, options, 8)
+        };
+        let (_, _, single) = run(KIWIPETE, 1);
+        let (_, _, smp) = run(KIWIPETE, 4);
+        // helpers add their nodes on top of the main thread's
+        assert!(smp > single, "{smp} nodes with 4 threads, {single} with 1");
+
+        // mate in 3, which the helpers' results must not change
+        let fen = "6k1/5p1p/6p1/8/8/8/1Q3PPP/1R4K1 w - - 0 1";
+        let (single_score, single_pv, _) = run(fen, 1);
+        let (smp_score, smp_pv, _) = run(fen, 4);
+        assert_eq!(single_score, CHECKMATE_SCORE - 5);
+        assert_eq!(smp_score, single_score);
+        assert_eq!(smp_pv[0], single_pv[0]);
+    }
+
+    #[test]
+    fn test_limit_check_extensions() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // black is a knight down, but the queen has a perpetual check on the exposed king
+        let fen = "7k/6pp/8/8/8/N7/PP3q1P/R1R4K b - - 0 1";
+        let run = |limit_check_extensions| {
+            let options = SearchOptions {
+                limit_check_extensions,
+                ..Default::default()
+            };
+            let (score, pv, nodes) = search_with(fen, options, 12);
+            (score, pv[0], nodes)
+        };
+
+        let (full_score, full_move, full_nodes) = run(false);
+        let (_, limited_move, limited_nodes) = run(true);
+        // following every check finds the repetition
+        assert!(full_score.abs() <= 8, "{full_score} without a limit");
+        // the limit stops short of it, but keeps the tree small and still starts the checks
+        assert!(
+            limited_nodes < full_nodes,
+            "{limited_nodes} nodes limited, {full_nodes} without"
+        );
+        assert_eq!(limited_move, full_move);
+    }
+
+    #[test]
+    fn test_lmr_table() {
+        // the pregenerated table uses the tuned 1.2232 and 1.9965, which the options round to
+        // 122 and 200 hundredths. the exact coefficients reduce a little more, so where the
+        // formula lands just under a whole ply the pregenerated table is one ply deeper
+        let computed = lmr_formula(LMR_BASE, LMR_DIVISOR);
+        let mut differences = 0;
+        for d in 1..64 {
+            for m in 1..64 {
+                let difference = LMR[d][m] - computed[d][m];
+                assert!((0..=1).contains(&difference), "depth {d} move {m}");
+                differences += difference as usize;
+            }
+        }
+        assert!(differences < 64, "{differences} reductions differ");
+        assert!(lmr_table(0, 10000).iter().flatten().all(|&r| r == 0));
+
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let run = |lmr_base, lmr_divisor| {
+            let options = SearchOptions {
+                lmr_base,
+                lmr_divisor,
+                ..Default::default()
+            };
+            nodes_with(options, 7)
+        };
+        let reduced = run(LMR_BASE, LMR_DIVISOR);
+        let unreduced = run(0, 10000);
+        assert!(
+            unreduced > reduced,
+            "{unreduced} nodes without LMR, {reduced} with"
+        );
+    }
+
+    #[test]
+    fn test_capture_history_lmr() {
+        let board = Board::from_fen(KIWIPETE).unwrap();
+        let capture = Move::from_pair(&board, "e5f7");
+        let options = SearchOptions {
+            capture_history_lmr_divisor: HISTORY_LMR_DIVISOR,
+            ..Default::default()
+        };
+        let reductions = with_big_stack(move || {
+            let mut search = Search::new(board).tt_size_mb(1).options(options);
+            [-HISTORY_MAX, 0, HISTORY_MAX].map(|history| {
+                search.thread_data.capture_history_tables[Color::White][capture] = history;
+                search.capture_reduction(capture, Color::White, 10, 20, false)
+            })
+        });
+
+        // a late capture that keeps failing is reduced more than one that keeps working
+        let [bad, neutral, good] = reductions;
+        assert!(bad > neutral && neutral > good, "{reductions:?}");
+
+        // and the reductions change which nodes the search visits
+        let nodes = |capture_history_lmr_divisor| {
+            nodes_with(
+                SearchOptions {
+                    capture_history_lmr_divisor,
+                    ..Default::default()
+                },
+                8,
+            )
+        };
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        assert_ne!(nodes(HISTORY_LMR_DIVISOR), nodes(0));
+    }
+
+    #[test]
+    fn test_quiesce_ignores_bogus_tt_move() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        with_big_stack(|| {
+            // the only capture, Bxc4, is illegal with the bishop pinned to the king
+            let board = Board::from_fen("4k3/4r3/8/8/2n5/8/4B3/4K3 w - - 0 1").unwrap();
+            let mut search = Search::new(board);
+            let mut pv = PrincipalVariation::new();
+            let quiesce =
+                |search: &mut Search, pv: &mut PrincipalVariation, tt: &TranspositionTable| {
+                    search.quiesce::<MainThread>(&board, MINUS_INF, INF, 0, pv, tt)
+                };
+            let clean = quiesce(&mut search, &mut pv, &TranspositionTable::new(1));
+
+            let pinned = Move::new(Bishop, Square::E2, Square::C4, Pawn);
+            let not_pseudolegal = Move::new(Queen, Square::D1, Square::D8, Pawn);
+            for bogus in [pinned, not_pseudolegal] {
+                // a lower bound that can't cut, so only the move is used
+                let tt = TranspositionTable::new(1);
+                tt.set(
+                    board.hash(),
+                    bogus,
+                    0,
+                    MINUS_INF / 2,
+                    NodeType::LowerBound,
+                    false,
+                );
+                assert_eq!(quiesce(&mut search, &mut pv, &tt), clean, "{bogus}");
+                assert!(board.is_legal_line(pv.moves()), "{pv}");
+            }
+        });
+    }
+
+    #[test]
+    fn test_delta_pruning_keeps_checks() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        with_big_stack(|| {
+            // Rxa4+ is the only capture, and falls far short of alpha
+            let board = Board::from_fen("k7/8/8/8/p7/8/8/R3K3 w - - 0 1").unwrap();
+            let nodes = |delta_pruning_keeps_checks| {
+                ABORT_SEARCH.store(false, Ordering::Relaxed);
+                let options = SearchOptions {
+                    delta_pruning_keeps_checks,
+                    ..Default::default()
+                };
+                let mut search = Search::new(board).options(options);
+                let tt = TranspositionTable::new(1);
+                let mut pv = PrincipalVariation::new();
+                search.quiesce::<MainThread>(&board, 3000, 3001, 0, &mut pv, &tt);
+                search.local_nodes
+            };
+            assert_eq!(nodes(false), 1);
+            assert_eq!(nodes(true), 2);
+        });
+    }
+
+    #[test]
+    fn test_analyse_mode_zugzwang() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // only the quiet Kh6 keeps the pressure on, late move pruning skips it for a long time
+        let fen = "1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1";
+        let (score, pv) = run_search(move || {
+            Search::new(Board::from_fen(fen).unwrap())
+                .tt_size_mb(1)
+                .options(SearchOptions::default().analyse_mode())
+                .max_depth(Some(8))
+        });
+        assert_eq!(pv[0], Move::new(King, Square::G5, Square::H6, Pawn));
+        assert!(score > 200, "{score}");
+    }
+
+    #[test]
+    fn test_nmp_verification() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // plain null move pruning doesn't find the mate in 7 here until depth 13
+        let fen = "8/k6P/8/2R5/8/8/6Kb/8 w - - 0 1";
+        let search = |verification_depth| {
+            run_search(move || {
+                Search::new(Board::from_fen(fen).unwrap())
+                    .tt_size_mb(1)
+                    .options(SearchOptions {
+                        nmp_verification_depth: verification_depth,
+                        ..Default::default()
+                    })
+                    .max_depth(Some(12))
+            })
+        };
+
+        let (score, _) = search(0);
+        assert!(score < CHECKMATE_SCORE - SEARCH_MAX_PLY as i16, "{score}");
+        let (score, _) = search(4);
+        assert_eq!(score, CHECKMATE_SCORE - 13);
+    }
+
+    #[test]
+    fn test_history_persists() {
+        let _lock = SEARCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let start = Board::new();
+        let next = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
+            .unwrap();
+        let history_sum = move |search: &Search| {
+            let histories = &search.thread_data.history_tables[Color::White];
+            start
+                .legal_moves()
+                .inner()
+                .iter()
+                .map(|smv| histories[smv.mv].unsigned_abs() as u64)
+                .sum::<u64>()
+        };
+
+        with_big_stack(move || {
+            let mut search = Search::new(start).tt_size_mb(1).max_depth(Some(8));
+            search.smp_search();
+            let warm = history_sum(&search);
+            assert!(warm > 0);
+
+            // moving on to the next position keeps what the last search learned
+            search.set_position(next, vec![start.hash()]);
+            assert_eq!(history_sum(&search), warm);
+            let (_, pv, _) = search.smp_search();
+            assert!(next.is_legal_line(pv.moves()));
+            assert!(history_sum(&search) > 0);
+
+            search.new_game();
+            assert_eq!(history_sum(&search), 0);
+        });
+    }
+
+    #[test]
+    fn test_legal_pv_fallback() {
+        with_big_stack(|| {
+            let board = Board::new();
+            let search = Search::new(board).tt_size_mb(1);
+            let e4 = Move::new(Pawn, Square::E2, Square::E4, Pawn);
+            let e5 = Move::new(Pawn, Square::E7, Square::E5, Pawn);
+            let illegal = Move::new(Rook, Square::A1, Square::A5, Pawn);
+
+            let mut pv = PrincipalVariation::new();
+            pv.push(e4);
+            pv.push(e5);
+            assert_eq!(search.legal_pv(pv).moves(), &[e4, e5]);
+
+            // a corrupted tail is dropped
+            pv.push(illegal);
+            assert_eq!(search.legal_pv(pv).moves(), &[e4, e5]);
+
+            // a corrupted first move falls back to a legal move, the TT move if there is one
+            let mut pv = PrincipalVariation::new();
+            pv.push(illegal);
+            let fallback = search.legal_pv(pv);
+            assert_eq!(fallback.len(), 1);
+            assert!(board.legal_moves().contains(fallback[0]));
+
+            let d4 = Move::new(Pawn, Square::D2, Square::D4, Pawn);
+            search.transposition_table.read().unwrap().set(
+                board.hash(),
+                d4,
+                4,
+                20,
+                NodeType::Exact,
+                true,
+            );
+            assert_eq!(search.legal_pv(pv).moves(), &[d4]);
+        });
+    }
+}
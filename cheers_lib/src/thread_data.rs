@@ -13,6 +13,10 @@ pub struct SearchStackEntry {
     pub quiets: MoveList,
     pub current_move: Move,
     pub killer_moves: KillerMoves<NUM_KILLER_MOVES>,
+    // check extensions on the line from the root to this node
+    pub check_extensions: u8,
+    // whether `current_move` gave a check that SEE says loses material
+    pub losing_check: bool,
 }
 impl Default for SearchStackEntry {
     fn default() -> Self {
@@ -22,6 +26,8 @@ impl Default for SearchStackEntry {
             quiets: MoveList::default(),
             current_move: Move::null(),
             killer_moves: KillerMoves::default(),
+            check_extensions: 0,
+            losing_check: false,
         }
     }
 }
@@ -32,7 +38,7 @@ impl SearchStackEntry {
     }
 }
 
-const CONTHIST_MAX: usize = 2;
+pub const CONTHIST_MAX: usize = 2;
 #[derive(Clone)]
 pub struct ThreadData {
     pub search_stack: Box<[SearchStackEntry]>,
@@ -154,18 +160,26 @@ impl ThreadData {
         }
     }
 
+    // continuation history of a move following the move played `offset + 1` plies earlier
+    pub fn get_conthist(
+        &self,
+        mv: Move,
+        current_player: Color,
+        ply: usize,
+        offset: usize,
+    ) -> Option<i16> {
+        ply.checked_sub(offset + 1)
+            .map(|p| self.search_stack[p].current_move)
+            .map(|cm| self.conthist_tables[offset][current_player][cm.piece()][cm.to()][mv])
+    }
+
     pub fn get_quiet_history(&self, mv: Move, current_player: Color, ply: usize) -> i16 {
         let mut history = self.history_tables[current_player][mv];
-        if ply > 0 {
-            for i in 0..CONTHIST_MAX {
-                if let Some(cm) = ply
-                    .checked_sub(i + 1)
-                    .map(|p| self.search_stack[p].current_move)
-                {
-                    history += self.conthist_tables[i][current_player][cm.piece()][cm.to()][mv];
-                } else {
-                    break;
-                }
+        for i in 0..CONTHIST_MAX {
+            if let Some(h) = self.get_conthist(mv, current_player, ply, i) {
+                history += h;
+            } else {
+                break;
             }
         }
         history
@@ -206,11 +220,8 @@ impl ThreadData {
             let mut score = QUIET_SCORE + (self.history_tables[current_player][mv] as i32);
 
             for i in 0..CONTHIST_MAX {
-                if let Some(cm) = ply
-                    .checked_sub(i + 1)
-                    .map(|p| self.search_stack[p].current_move)
-                {
-                    score += self.conthist_tables[i][current_player][cm.piece()][cm.to()][mv] as i32
+                if let Some(h) = self.get_conthist(mv, current_player, ply, i) {
+                    score += h as i32
                 } else {
                     break;
                 }
@@ -20,6 +20,23 @@ impl<T, const N: usize> IndexMut<Color> for [T; N] {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameOutcome {
+    WhiteWin,
+    BlackWin,
+    Draw,
+}
+
+impl GameOutcome {
+    // the result of `player` being checkmated
+    pub fn loss_for(player: Color) -> Self {
+        match player {
+            Color::White => Self::BlackWin,
+            Color::Black => Self::WhiteWin,
+        }
+    }
+}
+
 pub struct White;
 pub struct Black;
 
@@ -56,3 +56,69 @@ pub fn zobrist_ep(mask: BitBoard) -> u64 {
         *ZOBRIST_NUMBERS.get_unchecked(64 * 6 * 2 + 1 + 16 + mask.first_square().file())
     }
 }
+
+// the numbers above come from a table generated once and checked into cheers_pregen, so hashes
+// are identical across runs and machines. this generates an alternative set from a fixed-seed
+// splitmix64 stream, for testing hash collisions against a different set of keys
+pub fn seed(seed: u64) -> [u64; 793] {
+    let mut state = seed;
+    let mut numbers = [0; 793];
+    for n in numbers.iter_mut() {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        *n = z ^ (z >> 31);
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use cheers_bitboards::{BitBoard, Square};
+
+    use super::*;
+
+    #[test]
+    fn test_zobrist_constants() {
+        // catches an accidental regeneration or reordering of the table
+        assert_eq!(
+            zobrist_piece::<White>(Piece::Pawn, Square::A1),
+            17788498082838341076
+        );
+        assert_eq!(
+            zobrist_piece::<White>(Piece::Pawn, Square::B1),
+            8282654784678778713
+        );
+        assert_eq!(
+            zobrist_piece::<Black>(Piece::Pawn, Square::A1),
+            2345391397372306680
+        );
+        assert_eq!(
+            zobrist_piece::<Black>(Piece::King, Square::E8),
+            4609336795348116854
+        );
+        assert_eq!(zobrist_player(), 17459210216407110748);
+        assert_eq!(
+            zobrist_castling([[BitBoard::empty(); 2]; 2]),
+            7717648095884914907
+        );
+        assert_eq!(
+            zobrist_castling([[Square::A1.bitboard(); 2]; 2]),
+            13841430342538888826
+        );
+    }
+
+    #[test]
+    fn test_zobrist_seed() {
+        let a = seed(0);
+        assert_eq!(a, seed(0));
+        assert_ne!(a, seed(1));
+        assert_eq!(a[0], 0xE220A8397B1DCDAF);
+
+        let mut sorted = a.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), a.len());
+    }
+}
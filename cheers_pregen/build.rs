@@ -20,9 +20,18 @@ fn main() {
 
     let mut index = 0;
     let mut sliding_attack_tables = vec![BitBoard::empty(); 107648];
-    let rook_magics = generate_rook_magics(&mut sliding_attack_tables, &mut index);
+    let mut pext_attack_tables = vec![BitBoard::empty(); 107648];
+    let rook_magics = generate_rook_magics(
+        &mut sliding_attack_tables,
+        &mut pext_attack_tables,
+        &mut index,
+    );
     println!("rooks");
-    let bishop_magics = generate_bishop_magics(&mut sliding_attack_tables, &mut index);
+    let bishop_magics = generate_bishop_magics(
+        &mut sliding_attack_tables,
+        &mut pext_attack_tables,
+        &mut index,
+    );
     println!("bishops");
     let lookup_tables_out = Path::new("src/lookup_tables.rs");
     fs::write(
@@ -34,6 +43,7 @@ fn main() {
             generate_pawn_push_tables(),
             generate_pawn_attack_tables(),
             sliding_attack_tables,
+            pext_attack_tables,
             generate_between_table(),
             rook_magics,
             bishop_magics,
@@ -151,9 +161,6 @@ mod consts {
     pub const SEVENTH_RANK: BitBoard = BitBoard(0x00FF000000000000);
     pub const EIGHTH_RANK: BitBoard = BitBoard(0xFF00000000000000);
 
-    pub const LIGHT_SQUARES: BitBoard = BitBoard(0x5555555555555555);
-    pub const DARK_SQUARES: BitBoard = BitBoard(0xAAAAAAAAAAAAAAAA);
-
     pub const LONG_DIAGONALS: BitBoard = BitBoard(0x8142241818244281);
 }
 pub use self::consts::*;
@@ -232,21 +239,29 @@ pub struct MagicSquare {
 }
 
 /// Generates magic numbers/shifts to look up rook attacks from each square
-fn generate_rook_magics(attack_table: &mut [BitBoard], index: &mut usize) -> [MagicSquare; 64] {
+fn generate_rook_magics(
+    attack_table: &mut [BitBoard],
+    pext_table: &mut [BitBoard],
+    index: &mut usize,
+) -> [MagicSquare; 64] {
     let mut rook_magic = [MagicSquare::default(); 64];
 
     for square in 0..64 {
-        rook_magic[square] = find_magic(square, false, attack_table, index).unwrap();
+        rook_magic[square] = find_magic(square, false, attack_table, pext_table, index).unwrap();
     }
     rook_magic
 }
 
 /// Generates magic numbers/shifts to look up bishop attacks from each square
-fn generate_bishop_magics(attack_table: &mut [BitBoard], index: &mut usize) -> [MagicSquare; 64] {
+fn generate_bishop_magics(
+    attack_table: &mut [BitBoard],
+    pext_table: &mut [BitBoard],
+    index: &mut usize,
+) -> [MagicSquare; 64] {
     let mut bishop_magic = [MagicSquare::default(); 64];
 
     for square in 0..64 {
-        bishop_magic[square] = find_magic(square, true, attack_table, index).unwrap();
+        bishop_magic[square] = find_magic(square, true, attack_table, pext_table, index).unwrap();
     }
     bishop_magic
 }
@@ -255,6 +270,7 @@ fn find_magic(
     square: usize,
     bishop: bool,
     attack_table: &mut [BitBoard],
+    pext_table: &mut [BitBoard],
     index: &mut usize,
 ) -> Result<MagicSquare, String> {
     let mask = if bishop {
@@ -313,6 +329,9 @@ fn find_magic(
                 assert!(elem.is_empty())
             }
             attack_table[*index..(*index + used.len())].copy_from_slice(&used);
+            // blocking masks are enumerated in pext order, so the same slot indexed by pext
+            // instead of the magic hash is just the attacks in order
+            pext_table[*index..(*index + used.len())].copy_from_slice(&attack_masks);
             *index += used.len();
 
             return result;
@@ -2,7 +2,7 @@ use rayon::prelude::*;
 
 use cheers_lib::{
     board::{
-        evaluate::{EvalParams, EvalTrace},
+        evaluate::{EvalParams, EvalTrace, SCALE_NORMAL},
         Board,
     },
     hash_tables::PawnHashTable,
@@ -20,16 +20,29 @@ pub struct TuningTuple {
 #[derive(Clone)]
 pub struct TuningEntry {
     phase: u16,
+    // index of the endgame scale parameter the engine applied, none for SCALE_NORMAL
+    eg_scale: Option<u16>,
     //static_score: i16,
     //score: i16,
     // turn: ColorIndex,
     result: GameResult,
     tuples: Vec<TuningTuple>,
 }
+
 fn sigmoid(s: f64, k: f64) -> f64 {
     1.0 / (1.0 + (-k * s / 400.0).exp())
 }
 
+// the scales aren't linear terms, so the tuner keeps which one was used and adjusts its
+// endgame half directly
+fn endgame_scale_index(trace: &EvalTrace) -> Option<u16> {
+    trace
+        .endgame_scales
+        .iter()
+        .position(|sides| sides != &[0, 0])
+        .map(|kind| (EvalParams::ENDGAME_SCALES_INDEX + 2 * kind + 1) as u16)
+}
+
 #[allow(dead_code)]
 pub fn mf_to_entry(mf: &str) -> TuningEntry {
     let mut split = mf.split('|');
@@ -52,7 +65,7 @@ pub fn mf_to_entry(mf: &str) -> TuningEntry {
     let (_, trace) = game.evaluate_impl::<EvalTrace>(&mut pawn_hash_table);
 
     let tuples = trace
-        .to_array()
+        .to_array()[..EvalParams::ENDGAME_SCALES_INDEX]
         .chunks_exact(2)
         .enumerate()
         .filter(|(_i, c)| c[0] != c[1])
@@ -71,6 +84,7 @@ pub fn mf_to_entry(mf: &str) -> TuningEntry {
 
     TuningEntry {
         phase: phase as u16,
+        eg_scale: endgame_scale_index(&trace),
         result: GameResult::from_f64(result),
         tuples,
     }
@@ -94,7 +108,7 @@ pub fn book_to_entry(book: &str) -> TuningEntry {
     let (_, trace) = game.evaluate_impl::<EvalTrace>(&mut pawn_hash_table);
 
     let tuples = trace
-        .to_array()
+        .to_array()[..EvalParams::ENDGAME_SCALES_INDEX]
         .chunks_exact(2)
         .enumerate()
         .filter(|(_i, c)| c[0] != c[1])
@@ -113,6 +127,7 @@ pub fn book_to_entry(book: &str) -> TuningEntry {
 
     TuningEntry {
         phase: phase as u16,
+        eg_scale: endgame_scale_index(&trace),
         result: GameResult::from_f64(result),
         tuples,
     }
@@ -142,7 +157,7 @@ pub fn epd_to_entry(epd: &str) -> TuningEntry {
     let (_, trace) = game.evaluate_impl::<EvalTrace>(&mut pawn_hash_table);
 
     let tuples = trace
-        .to_array()
+        .to_array()[..EvalParams::ENDGAME_SCALES_INDEX]
         .chunks_exact(2)
         .enumerate()
         .filter(|(_i, c)| c[0] != c[1])
@@ -161,12 +176,14 @@ pub fn epd_to_entry(epd: &str) -> TuningEntry {
 
     TuningEntry {
         phase: phase as u16,
+        eg_scale: endgame_scale_index(&trace),
         result: GameResult::from_f64(result),
         tuples,
     }
 }
 
-pub fn linear_evaluation(entry: &TuningEntry, params: &[f64; EvalParams::LEN]) -> f64 {
+// the midgame and unscaled endgame scores
+fn linear_scores(entry: &TuningEntry, params: &[f64; EvalParams::LEN]) -> (f64, f64) {
     let mut mg = 0f64;
     let mut eg = 0f64;
     for tuple in entry.tuples.iter() {
@@ -176,6 +193,20 @@ pub fn linear_evaluation(entry: &TuningEntry, params: &[f64; EvalParams::LEN]) -
         mg += mg_weight * (tuple.white_coeff - tuple.black_coeff) as f64;
         eg += eg_weight * (tuple.white_coeff - tuple.black_coeff) as f64;
     }
+    (mg, eg)
+}
+
+// the fraction of the endgame score kept
+fn eg_scale(entry: &TuningEntry, params: &[f64; EvalParams::LEN]) -> f64 {
+    entry
+        .eg_scale
+        .map_or(SCALE_NORMAL as f64, |i| params[i as usize])
+        / SCALE_NORMAL as f64
+}
+
+pub fn linear_evaluation(entry: &TuningEntry, params: &[f64; EvalParams::LEN]) -> f64 {
+    let (mg, eg) = linear_scores(entry, params);
+    let eg = eg * eg_scale(entry, params);
     ((256.0 - entry.phase as f64) * mg + entry.phase as f64 * eg) / 256.0
 }
 
@@ -216,6 +247,7 @@ pub fn calculate_gradient(
                 let eval = linear_evaluation(b, eval_params);
                 let s = sigmoid(eval, k);
                 let base = (b.result.into_f64() - s) * s * (s - 1.0);
+                let scale = eg_scale(b, eval_params);
 
                 for tuple in &b.tuples {
                     let i = tuple.index;
@@ -224,8 +256,13 @@ pub fn calculate_gradient(
                         * (tuple.white_coeff - tuple.black_coeff) as f64;
                     a[i as usize + 1] += base
                         * (b.phase as f64 / 256.0)
+                        * scale
                         * (tuple.white_coeff - tuple.black_coeff) as f64;
                 }
+                if let Some(i) = b.eg_scale {
+                    let (_, eg) = linear_scores(b, eval_params);
+                    a[i as usize] += base * (b.phase as f64 / 256.0) * eg / SCALE_NORMAL as f64;
+                }
                 a
             },
         )